@@ -0,0 +1,72 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use ultraviolet::DVec3;
+
+use fractal::palette::{Gradient, Lut};
+use fractal::{iter_to_color_lut, palette, GridCell, Sim, SimConfig};
+
+/// A finished render of the default frame, to draw over and over
+fn finished_sim() -> Sim {
+    let config = SimConfig::builder()
+        .fb_dims(1080, 617)
+        .max_iters(1_000)
+        .build()
+        .expect("Expected a non-empty framebuffer");
+
+    let mut sim = Sim::new(config);
+    sim.run_until_done(u32::MAX);
+    sim
+}
+
+/// Compare drawing the plain palette through `draw` against a table from `iter_to_color_lut`
+fn lut_draw(c: &mut Criterion) {
+    let sim = finished_sim();
+    let dims = sim.config().fb_dims;
+    let mut framebuffer = vec![0; (dims.x * dims.y) as usize];
+    let lut = iter_to_color_lut(sim.config().max_iters, palette::plain_color, DVec3::zero());
+
+    let mut group = c.benchmark_group("draw_plain");
+    group.bench_function("palette", |b| {
+        b.iter(|| sim.draw(&mut framebuffer, &palette::with_plain_colors))
+    });
+    group.bench_function("lut", |b| {
+        b.iter(|| sim.draw_with_lut(&mut framebuffer, &lut))
+    });
+    group.finish();
+}
+
+/// Compare sampling a gradient for every cell against drawing with a `Lut` built from it
+fn gradient_draw(c: &mut Criterion) {
+    let sim = finished_sim();
+    let dims = sim.config().fb_dims;
+    let mut framebuffer = vec![0; (dims.x * dims.y) as usize];
+
+    let max_iters = sim.config().max_iters;
+    let gradient = Gradient::new(vec![
+        DVec3::new(0., 7., 100.),
+        DVec3::new(32., 107., 203.),
+        DVec3::new(237., 255., 255.),
+        DVec3::new(255., 170., 0.),
+        DVec3::new(0., 2., 0.),
+    ]);
+    let lut = Lut::build(&gradient, max_iters);
+    let encoded = lut.encoded();
+
+    let sample = |cell: &GridCell| {
+        if cell.has_escaped {
+            gradient.sample((cell.iters as f64 / max_iters as f64).min(1.))
+        } else {
+            DVec3::zero()
+        }
+    };
+
+    let mut group = c.benchmark_group("draw_gradient");
+    group.bench_function("sample", |b| b.iter(|| sim.draw(&mut framebuffer, &sample)));
+    group.bench_function("lut", |b| b.iter(|| sim.draw(&mut framebuffer, &lut)));
+    group.bench_function("lut_encoded", |b| {
+        b.iter(|| sim.draw_with_lut(&mut framebuffer, &encoded))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, lut_draw, gradient_draw);
+criterion_main!(benches);
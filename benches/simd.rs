@@ -0,0 +1,35 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use fractal::{Sim, SimConfig};
+
+/// Compare running a fresh render to the end with `update` against `update_simd_n`
+fn simd_step(c: &mut Criterion) {
+    let config = SimConfig::builder()
+        .fb_dims(640, 366)
+        .max_iters(1_000)
+        .build()
+        .expect("Expected a non-empty framebuffer");
+
+    let mut group = c.benchmark_group("run_until_done");
+    group.sample_size(10);
+
+    group.bench_function("update", |b| {
+        b.iter_batched(
+            || Sim::new(config),
+            |mut sim| sim.run_until_done(u32::MAX),
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("update_simd_n", |b| {
+        b.iter_batched(
+            || Sim::new(config),
+            |mut sim| while sim.update_simd_n(64).active > 0 {},
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, simd_step);
+criterion_main!(benches);
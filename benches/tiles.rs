@@ -0,0 +1,31 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use ultraviolet::UVec2;
+
+use fractal::{palette, Sim, SimConfig};
+
+/// Compare rendering tiles across the pool in Hilbert order against one at a time by rows
+fn tile_order(c: &mut Criterion) {
+    let config = SimConfig::builder()
+        .fb_dims(512, 384)
+        .max_iters(1_000)
+        .build()
+        .expect("Expected a non-empty framebuffer");
+    let tile_dims = UVec2::broadcast(64);
+
+    let mut group = c.benchmark_group("render_tiled");
+    group.sample_size(10);
+
+    group.bench_function("hilbert", |b| {
+        b.iter(|| Sim::render_tiled(&config, tile_dims, palette::with_plain_colors, |_, _, _| {}))
+    });
+    group.bench_function("rows", |b| {
+        b.iter(|| {
+            Sim::render_tiled_by_rows(&config, tile_dims, palette::with_plain_colors, |_, _, _| {})
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, tile_order);
+criterion_main!(benches);
@@ -0,0 +1,113 @@
+//! Averaging several renders into one frame
+
+use ultraviolet::{DVec3, UVec2};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::{encode, GridCell, Sim, SimConfig};
+
+/// Sums colors from several renders of the same dimensions, so they can be averaged into one frame
+pub struct Accumulator {
+    dims: UVec2,
+    sum: Vec<[f32; 3]>,
+    count: u32,
+}
+
+impl Accumulator {
+    pub fn new(dims: UVec2) -> Self {
+        Self {
+            dims,
+            sum: vec![[0.; 3]; (dims.x * dims.y) as usize],
+            count: 0,
+        }
+    }
+
+    /// Color every pixel of `sim` with `color` and add it to the running sum
+    ///
+    /// Colors are clamped into `[0, 1]` first, just like `Sim::draw` does. Supersampled pixels
+    /// average their cells before clamping, also like `Sim::draw`.
+    pub fn add<ColorFn>(&mut self, sim: &Sim, color: ColorFn)
+    where
+        ColorFn: Fn(&GridCell) -> DVec3 + Sync,
+    {
+        assert_eq!(sim.config.fb_dims, self.dims);
+
+        let add = |(i, sum): (usize, &mut [f32; 3])| {
+            let c = sim
+                .pixel_color(i, &color)
+                .clamped(DVec3::broadcast(0.), DVec3::broadcast(1.));
+            sum[0] += c.x as f32;
+            sum[1] += c.y as f32;
+            sum[2] += c.z as f32;
+        };
+
+        #[cfg(feature = "rayon")]
+        {
+            self.sum.par_iter_mut().enumerate().for_each(add);
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            self.sum.iter_mut().enumerate().for_each(add);
+        }
+
+        self.count += 1;
+    }
+
+    /// Average everything added so far into a framebuffer
+    pub fn resolve(&self) -> Vec<u32> {
+        let n = self.count.max(1) as f64;
+
+        self.sum
+            .iter()
+            .map(|&[r, g, b]| encode(DVec3::new(r as f64, g as f64, b as f64) / n))
+            .collect()
+    }
+}
+
+/// Render one motion-blurred frame of a zoom into the center of `base`'s frame
+///
+/// The zoom starts at `base`'s frame and ends at that frame scaled by `scale_to` around its center,
+/// so `scale_to < 1` zooms in. `subframes` frames with geometrically interpolated scales are
+/// rendered and averaged together. Only the frame changes between subframes: the kind, formula,
+/// iteration limit, supersampling and everything else come from `base`.
+///
+/// Each subframe is a full render, run until it's done (or for `max_iters` plus a few steps), so
+/// this costs `subframes` times as much as rendering a single frame.
+pub fn render_zoom_blurred<ColorFn>(
+    base: &SimConfig,
+    scale_to: f64,
+    subframes: u32,
+    color: ColorFn,
+) -> Vec<u32>
+where
+    ColorFn: Fn(&GridCell) -> DVec3 + Sync,
+{
+    let center = 0.5 * (base.frame_min + base.frame_max);
+    let half = 0.5 * (base.frame_max - base.frame_min);
+    let mut accumulator = Accumulator::new(base.fb_dims);
+
+    // Same cap as `gen`: escaped cells only need a handful of steps past `max_iters`
+    let max_steps = base.max_iters.saturating_add(64);
+
+    for i in 0..subframes {
+        let t = if subframes > 1 {
+            i as f64 / (subframes - 1) as f64
+        } else {
+            0.
+        };
+        let half = half * scale_to.powf(t);
+
+        let mut sim = Sim::new(SimConfig {
+            frame_min: center - half,
+            frame_max: center + half,
+            ..*base
+        });
+        sim.run_until_done(max_steps);
+
+        accumulator.add(&sim, &color);
+    }
+
+    accumulator.resolve()
+}
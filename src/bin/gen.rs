@@ -1,41 +1,243 @@
-use ultraviolet::UVec2;
+use ultraviolet::{DVec2, UVec2};
 
 use std::time::{Duration, Instant};
 
-use fractal::{make_default_frame, palette, Sim, SimConfig};
+use fractal::{
+    buddhabrot, make_default_frame, make_square_frame, palette, CoordTransform, Formula,
+    FractalKind, GridCell, Sim, SimConfig, DEFAULT_MAX_ITERS, DEFAULT_STEP_RADIUS_SQR,
+};
+
+/// Render a handful of interesting locations into a single captioned image
+fn gallery() {
+    const THUMB_DIM: u32 = 256;
+    const STEPS: u32 = 1_000;
+
+    // Thumbnails are rendered from a grid this many times larger, so they come out smooth
+    const OVERSAMPLE: u32 = 2;
+
+    let (default_min, default_max) = make_default_frame();
+    let aspect_ratio = (default_max.x - default_min.x) / (default_max.y - default_min.y);
+    let square = UVec2::broadcast(OVERSAMPLE * THUMB_DIM);
+
+    let frame = |(frame_min, frame_max): (DVec2, DVec2), fb_dims: UVec2| SimConfig {
+        fb_dims,
+        frame_min,
+        frame_max,
+        transform: CoordTransform::Identity,
+        kind: FractalKind::Mandelbrot,
+        max_iters: STEPS,
+        power: 2,
+        formula: Formula::Standard,
+        samples_per_axis: 1,
+        step_radius_sqr: DEFAULT_STEP_RADIUS_SQR,
+    };
+
+    let locations = [
+        (
+            frame(
+                (default_min, default_max),
+                UVec2::new(square.x, (square.x as f64 / aspect_ratio) as u32),
+            ),
+            "default",
+        ),
+        (
+            frame(make_square_frame(DVec2::new(-0.722, 0.246), 0.019), square),
+            "-0.722,0.246",
+        ),
+        (
+            frame(
+                make_square_frame(DVec2::new(-1.25066, 0.02012), 1.7e-4),
+                square,
+            ),
+            "-1.25066,0.02012",
+        ),
+    ];
+
+    let filename = "gallery.png";
+    println!("Rendering {}", filename);
+
+    let (gallery, dims) =
+        fractal::render_gallery(&locations, palette::with_plain_colors, STEPS, THUMB_DIM);
+    fractal::save_framebuffer_png(filename, &gallery, dims).expect("Failed to save image");
+}
+
+/// Parse the value following `--name` on the command line, if it's there
+fn arg_value<T: std::str::FromStr>(name: &str) -> Option<T> {
+    std::env::args()
+        .skip_while(|arg| arg != name)
+        .nth(1)
+        .map(|value| {
+            value
+                .parse()
+                .unwrap_or_else(|_| panic!("Couldn't parse {:?} after {}", value, name))
+        })
+}
 
 fn main() {
+    if std::env::args().any(|arg| arg == "--gallery") {
+        gallery();
+        return;
+    }
+
     // See more frames here:
     // http://www.cuug.ab.ca/dewara/mandelbrot/Mandelbrowser.html
 
+    // Write out a preview of the render so far every this many steps, if set
+    let preview_every: Option<u32> = arg_value::<u32>("--preview-every").filter(|&n| n > 0);
+
+    // Iterate relative to a reference orbit, which stays sharp on deep zooms
+    let perturb = std::env::args().any(|arg| arg == "--perturb");
+
+    // Only iterate the borders of uniform rectangles, which the plain palette can't tell apart
+    let mariani_silver = std::env::args().any(|arg| arg == "--mariani-silver");
+
+    // Save 16 bits per channel, so smooth palettes don't band
+    let sixteen_bit = std::env::args().any(|arg| arg == "--16bit");
+
+    // Also print a preview this many characters wide, for when there's no image viewer handy
+    let terminal_cols: Option<u32> = arg_value::<u32>("--terminal").filter(|&n| n > 0);
+
     // "The" Mandelbrot View
     let (frame_min, frame_max) = make_default_frame();
     let aspect_ratio = (frame_max.x - frame_min.x) / (frame_max.y - frame_min.y);
+    let default_center = 0.5 * (frame_min + frame_max);
 
-    let width = 1080.;
-    let height = width / aspect_ratio;
-    let fb_dims = UVec2::new(width as u32, height as u32);
+    let width: u32 = arg_value("--width").unwrap_or(1080);
+    let height: u32 = arg_value("--height").unwrap_or((width as f64 / aspect_ratio) as u32);
 
-    let mut sim = Sim::new(SimConfig {
-        fb_dims,
-        frame_min,
-        frame_max,
+    let palette_name: String = arg_value("--palette").unwrap_or_else(|| "plain".to_string());
+    let named_palette = palette::by_name(&palette_name).unwrap_or_else(|| {
+        panic!(
+            "Unknown palette {:?}, expected one of {:?}",
+            palette_name,
+            palette::names()
+        )
     });
+    assert!(
+        !mariani_silver || palette_name == "plain",
+        "--mariani-silver only fills in iteration counts, so it needs --palette plain"
+    );
+
+    // Swap the plain or smooth palette's colors for random ones, which are the same for every seed
+    let random_seed: Option<u64> = arg_value("--random-colors");
+    let random_colors = random_seed.map(palette::random_colors);
+    let smooth = palette_name == "smooth";
+    assert!(
+        random_colors.is_none() || smooth || palette_name == "plain",
+        "--random-colors replaces the colors of --palette plain or smooth"
+    );
+    let palette = move |cell: &GridCell| match &random_colors {
+        Some(colors) if smooth => palette::smooth_colors(cell, colors),
+        Some(colors) => palette::plain_colors(cell, colors),
+        None => named_palette(cell),
+    };
+    let palette_name = match random_seed {
+        Some(seed) => format!("{} (random colors, seed {})", palette_name, seed),
+        None => palette_name,
+    };
+
+    // Render a view saved from the viewer, or re-render a PNG that we saved, if given one
+    let config_path: Option<String> = arg_value("--config");
+
+    // The builder defaults to the same frame, but keeps its pixels square
+    let config = match config_path {
+        Some(path) if path.ends_with(".png") => {
+            fractal::read_png_params(&path).expect("Failed to read a config from --config")
+        }
+        #[cfg(feature = "serde")]
+        Some(path) => SimConfig::load_from_path(&path).expect("Failed to load --config"),
+        #[cfg(not(feature = "serde"))]
+        Some(path) => panic!("Loading {:?} needs the serde feature", path),
+        None => SimConfig::builder()
+            .fb_dims(width, height)
+            .center(DVec2::new(
+                arg_value("--center-x").unwrap_or(default_center.x),
+                arg_value("--center-y").unwrap_or(default_center.y),
+            ))
+            .zoom(arg_value("--radius").unwrap_or(0.5 * (frame_max.x - frame_min.x)))
+            .max_iters(arg_value("--iters").unwrap_or(DEFAULT_MAX_ITERS))
+            .samples_per_axis(arg_value("--samples").unwrap_or(1))
+            .build()
+            .expect("Expected a non-empty framebuffer"),
+    };
+    let fb_dims = config.fb_dims;
+
+    // Plot where escaping orbits go, instead of how long each pixel takes to escape
+    if let Some(samples) = arg_value::<u32>("--buddhabrot").filter(|&n| n > 0) {
+        let filename: String = arg_value("--output")
+            .unwrap_or_else(|| format!("buddhabrot-{}x{}.png", fb_dims.x, fb_dims.y));
+        println!("Rendering {} from {} samples", filename, samples);
+
+        // The same seed always picks the same points, so renders can be reproduced
+        let seed: u64 = arg_value("--seed").unwrap_or(0);
+        let density = buddhabrot::render(config, samples, config.max_iters, seed);
+        let framebuffer = buddhabrot::tone_map(&density);
+        fractal::save_png(&filename, &framebuffer, &config, "buddhabrot")
+            .expect("Failed to save image");
+        return;
+    }
+
+    // Huge images don't fit in a single grid, so render them a tile at a time instead
+    if let Some(tile) = arg_value::<u32>("--tile").filter(|&n| n > 0) {
+        let filename: String = arg_value("--output")
+            .unwrap_or_else(|| format!("mandelbrot-{}x{}.png", fb_dims.x, fb_dims.y));
+        println!("Rendering {} in {}x{} tiles", filename, tile, tile);
+
+        fractal::save_png_tiled(
+            &filename,
+            &config,
+            UVec2::broadcast(tile),
+            palette,
+            &palette_name,
+        )
+        .expect("Failed to save image");
+        return;
+    }
+
+    // Cap how many threads the render uses, so it doesn't starve everything else
+    #[cfg(feature = "rayon")]
+    let mut sim = Sim::new_with_threads(config, arg_value("--threads").unwrap_or(0));
+
+    #[cfg(not(feature = "rayon"))]
+    let mut sim = Sim::new(config);
 
     let mut step_times: Vec<Duration> = vec![];
     let raw_begin = Instant::now();
-    let filename = format!("mandelbrot-{}x{}.png", fb_dims.x, fb_dims.y);
+    let filename: String = arg_value("--output")
+        .unwrap_or_else(|| format!("mandelbrot-{}x{}.png", fb_dims.x, fb_dims.y));
     println!("Rendering {}", filename);
 
-    // TODO: How do we know when we're done....?
-    let steps = 1_000;
-    for _ in 0..steps {
+    // Every cell either escapes or gives up at `max_iters`, and escaped cells only need a handful of
+    // steps to pass the coloring bailout, so this cap is never expected to kick in
+    let max_steps = config.max_iters.saturating_add(64);
+
+    let mut steps = 0;
+    let mut active = sim.active_count();
+    if mariani_silver {
+        let iterated = sim.update_mariani_silver();
+        println!("Iterated {} of {} cells", iterated, sim.cells().len());
+        active = 0;
+    }
+    while active > 0 && steps < max_steps {
+        steps += 1;
         let begin = Instant::now();
 
-        sim.update();
+        let stats = if perturb {
+            sim.update_perturbed()
+        } else {
+            sim.update()
+        };
+        active = stats.active;
 
         let end = Instant::now();
         step_times.push(end - begin);
+
+        if preview_every.is_some_and(|every| steps % every == 0) {
+            let preview = sim.progress_image(palette);
+            fractal::save_png("preview.png", &preview, &config, &palette_name)
+                .expect("Failed to save preview");
+            println!("Wrote preview.png after {} steps", steps);
+        }
     }
 
     let raw_end = Instant::now();
@@ -58,34 +260,25 @@ fn main() {
     dbg!(ave);
     dbg!(overhead);
 
+    if let Some(cols) = terminal_cols {
+        // Characters are about twice as tall as they are wide
+        let rows = (cols as f64 * fb_dims.y as f64 / fb_dims.x as f64 / 2.).round() as u32;
+        sim.render_to_terminal(palette, &mut std::io::stdout().lock(), cols, rows.max(1))
+            .expect("Failed to print preview");
+    }
+
     // Render and write out image
+    if sixteen_bit {
+        let rgba = sim.render_rgba16(palette);
+        fractal::save_png16(&filename, &rgba, &config, &palette_name)
+            .expect("Failed to save image");
+        return;
+    }
+
     let mut framebuffer: Vec<u32> = vec![0; (fb_dims.x * fb_dims.y) as usize];
 
-    let color = palette::with_plain_colors;
-    // let color = palette::with_smooth_stripes;
-    // let color = palette::with_lambert_and_colors;
-    // let color = palette::with_white_lambert;
-    // let color = palette::with_color_from_dz;
-    sim.draw(&mut framebuffer, color);
-
-    // Change format from 0RGB -> to RGBA, both 8-bit channels
-    // We'll always use 0xFF for alpha.
-    const A: u8 = 0xff;
-    for px in framebuffer.iter_mut() {
-        // Each pixel is encoded as 0RGB
-        let [z, r, g, b] = px.to_be_bytes();
-        assert_eq!(z, 0);
-
-        // Re-encode as RGBA
-        *px = u32::from_le_bytes([r, g, b, A]);
-    }
+    sim.draw(&mut framebuffer, &palette);
 
-    image::save_buffer(
-        filename,
-        bytemuck::cast_slice(&framebuffer),
-        fb_dims.x,
-        fb_dims.y,
-        image::ColorType::Rgba8,
-    )
-    .expect("Failed to save image");
+    fractal::save_png(&filename, &framebuffer, &config, &palette_name)
+        .expect("Failed to save image");
 }
@@ -1,9 +1,13 @@
-use minifb::{Key, KeyRepeat, ScaleMode, Window, WindowOptions};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, ScaleMode, Window, WindowOptions};
+use num::Complex;
 use ultraviolet::{DVec2, UVec2};
 
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use fractal::{make_default_frame, palette, Sim, SimConfig};
+use fractal::{fit_aspect_ratio, make_default_frame, palette, FractalKind, Sim, SimConfig};
+
+/// Constant for the Julia set that J toggles to
+const JULIA_C: Complex<f64> = Complex::new(-0.8, 0.156);
 
 // Pick a reasonable resolution that fits without on screen and matches the frame's aspect ratio
 fn pick_window_dims(min: DVec2, max: DVec2) -> UVec2 {
@@ -12,9 +16,6 @@ fn pick_window_dims(min: DVec2, max: DVec2) -> UVec2 {
     const SCALE: f64 = 0.8;
     let window_dims = SCALE * DVec2::new(1680., 1080.);
 
-    // This is the ratio of the widdth of the window to the height
-    // Greater than 1.0 is typical, and means the window is wider than it is tall.
-    let window_ratio = window_dims.x / window_dims.y;
     let frame_ratio: f64 = {
         let dx = max.x - min.x;
         let dy = max.y - min.y;
@@ -22,40 +23,199 @@ fn pick_window_dims(min: DVec2, max: DVec2) -> UVec2 {
         dx / dy
     };
 
-    // We want to scale our dims so that they fit in the window, while still being as large as
-    // we can get.
-    let (x, y): (f64, f64);
-
-    // We'll check the frame_ratio to tell which axis has to change.
-    // One of three things will happen:
-    use std::cmp::Ordering;
-    match frame_ratio
-        .partial_cmp(&window_ratio)
-        .expect("Expected comparabile dimensions - no NaNs!")
-    {
-        Ordering::Equal => {
-            // 1. The ratio happens to match the window's ratio, so we'll use it directly.
-            x = window_dims.x;
-            y = window_dims.y;
-        }
-        Ordering::Greater => {
-            // 2. The frame is relatively wider than the window, so use the window's width and scale our height
-            x = window_dims.x;
-            y = window_dims.x / frame_ratio;
-        }
-        Ordering::Less => {
-            // 3. The frame is relatively taller than the window, so use the window's height and scale our width
-            x = window_dims.y * frame_ratio;
-            y = window_dims.y;
+    fit_aspect_ratio(frame_ratio, window_dims)
+}
+
+/// How much each click zooms in or out by
+const CLICK_ZOOM: f64 = 2.;
+
+/// How far, in pixels, the mouse has to move with the button down to select a region
+const DRAG_THRESHOLD: f64 = 4.;
+
+/// How far each press of an arrow key pans, as a fraction of the frame's size
+const PAN_FRACTION: f64 = 0.1;
+
+/// How much each press of +/- zooms in or out by
+const KEY_ZOOM: f64 = 1.25;
+
+/// Where bookmarks are kept between runs
+#[cfg(feature = "serde")]
+const BOOKMARKS_PATH: &str = "bookmarks.json";
+
+/// Keys that recall bookmarks, or save them with Shift held
+const BOOKMARK_KEYS: [Key; 9] = [
+    Key::Key1,
+    Key::Key2,
+    Key::Key3,
+    Key::Key4,
+    Key::Key5,
+    Key::Key6,
+    Key::Key7,
+    Key::Key8,
+    Key::Key9,
+];
+
+/// How many views Backspace can go back through
+const HISTORY_LEN: usize = 100;
+
+/// How many frames a click's zoom takes to animate from the old frame to the new one
+const ZOOM_FRAMES: u32 = 30;
+
+/// A zoom in progress, moving smoothly from one frame to another over `ZOOM_FRAMES` frames
+#[derive(Copy, Clone, Debug)]
+struct ZoomAnimation {
+    from: SimConfig,
+    to: SimConfig,
+
+    /// How many frames of the animation have been shown so far
+    progress: u32,
+}
+
+impl ZoomAnimation {
+    fn new(from: SimConfig, to: SimConfig) -> Self {
+        Self {
+            from,
+            to,
+            progress: 0,
+        }
+    }
+
+    /// The frame that's `t` of the way through the animation, from `0` to `1`
+    ///
+    /// The radius is interpolated linearly in log space, so every frame zooms by the same factor,
+    /// instead of rushing at the start and crawling at the end. The center moves in step with the
+    /// radius, so the zoom heads straight for its target.
+    fn frame_at(&self, t: f64) -> (DVec2, DVec2) {
+        if t >= 1. {
+            return (self.to.frame_min, self.to.frame_max);
+        }
+
+        let half = |config: &SimConfig| 0.5 * (config.frame_max - config.frame_min);
+        let center = |config: &SimConfig| 0.5 * (config.frame_min + config.frame_max);
+        let (from_half, to_half) = (half(&self.from), half(&self.to));
+
+        let (from_r, to_r) = (from_half.x, to_half.x);
+        let r = (from_r.ln() + t * (to_r.ln() - from_r.ln())).exp();
+
+        // How far the radius has come, which is just `t` when it doesn't change
+        let s = if from_r == to_r {
+            t
+        } else {
+            (r - from_r) / (to_r - from_r)
+        };
+
+        let center = center(&self.from) + s * (center(&self.to) - center(&self.from));
+        let half = from_half * (r / from_r);
+
+        (center - half, center + half)
+    }
+}
+
+/// Frame of `config`, scaled by `1 / zoom` and recentered on `center`
+///
+/// Both sides shrink by the same factor, so the frame keeps its aspect ratio.
+fn zoom_frame(config: &SimConfig, center: DVec2, zoom: f64) -> (DVec2, DVec2) {
+    let half = 0.5 * (config.frame_max - config.frame_min) / zoom;
+    (center - half, center + half)
+}
+
+/// The corner opposite `start` of a selection dragged to `end`, grown to the aspect ratio of `dims`
+///
+/// Zooming to a selection with any other shape would stretch the image, so the selection grows
+/// along whichever axis is short to match.
+fn selection_corner(start: DVec2, end: DVec2, dims: UVec2) -> DVec2 {
+    let ratio = dims.x as f64 / dims.y as f64;
+    let d = end - start;
+    let width = d.x.abs().max(d.y.abs() * ratio);
+
+    start + DVec2::new(width.copysign(d.x), (width / ratio).copysign(d.y))
+}
+
+/// The frame covered by the selection between framebuffer positions `a` and `b` of `config`'s frame
+fn selection_frame(config: &SimConfig, a: DVec2, b: DVec2) -> (DVec2, DVec2) {
+    let size = config.frame_max - config.frame_min;
+    let dims = config.fb_dims;
+
+    // Like `SimConfig::idx_to_complex`, rows go down from `frame_max.y`
+    let point = |p: DVec2| {
+        let t = DVec2::new(p.x / dims.x as f64, 1. - p.y / dims.y as f64);
+        config.frame_min + t * size
+    };
+    let (a, b) = (point(a), point(b));
+
+    (a.min_by_component(b), a.max_by_component(b))
+}
+
+/// Invert the outline of the rectangle between framebuffer positions `a` and `b`, clipped to `dims`
+///
+/// Inverting stays visible over any palette.
+fn draw_selection(fb: &mut [u32], dims: UVec2, a: DVec2, b: DVec2) {
+    let clamp = |x: f64, n: u32| x.round().clamp(0., n as f64 - 1.) as u32;
+    let (x0, x1) = (clamp(a.x.min(b.x), dims.x), clamp(a.x.max(b.x), dims.x));
+    let (y0, y1) = (clamp(a.y.min(b.y), dims.y), clamp(a.y.max(b.y), dims.y));
+
+    let mut invert = |x: u32, y: u32| fb[(y * dims.x + x) as usize] ^= 0xff_ff_ff;
+
+    // Each pixel is only inverted once, even when the rectangle is a single row or column
+    for x in x0..=x1 {
+        invert(x, y0);
+        if y1 > y0 {
+            invert(x, y1);
+        }
+    }
+    for y in y0 + 1..y1 {
+        invert(x0, y);
+        if x1 > x0 {
+            invert(x1, y);
         }
     }
+}
 
-    // Sanity check because this logic took forever to get right.
-    assert!(x <= window_dims.x);
-    assert!(y <= window_dims.y);
+/// Window title describing the view of `config`, after it's been updated `steps` times
+///
+/// Zoom is relative to the default frame, so it starts out at `1`.
+fn window_title(config: &SimConfig, steps: u64) -> String {
+    let name = match config.kind {
+        FractalKind::Mandelbrot => "Mandelbrot",
+        FractalKind::Julia { .. } => "Julia",
+    };
+    let center = 0.5 * (config.frame_min + config.frame_max);
+    let (default_min, default_max) = make_default_frame();
+    let zoom = (default_max.x - default_min.x) / (config.frame_max.x - config.frame_min.x);
+
+    // Deeper zooms need more digits to tell neighboring views apart
+    let digits = 4 + zoom.log10().max(0.) as usize;
+
+    format!(
+        "{} | c=({:.*}, {:.*}) | zoom={:.1e} | iter={}",
+        name, digits, center.x, digits, center.y, zoom, steps
+    )
+}
 
-    // Round our chosen dimensions into integer coordinates and we're done!
-    UVec2::new(x.round() as u32, y.round() as u32)
+/// Write `framebuffer` to a timestamped PNG on a background thread, so the render loop keeps going
+///
+/// The filename encodes the center and radius of the frame, and the full config is embedded in the
+/// PNG's metadata, so `read_png_params` can reproduce the shot exactly.
+fn screenshot(framebuffer: &[u32], config: &SimConfig, palette_name: &'static str) {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let center = 0.5 * (config.frame_min + config.frame_max);
+    let radius = 0.5 * (config.frame_max.x - config.frame_min.x);
+    let path = format!(
+        "screenshot-{}_{}_{}_r{:e}.png",
+        timestamp, center.x, center.y, radius
+    );
+
+    let framebuffer = framebuffer.to_vec();
+    let config = *config;
+    std::thread::spawn(move || {
+        match fractal::save_png(&path, &framebuffer, &config, palette_name) {
+            Ok(()) => println!("Saved {}", path),
+            Err(err) => println!("Failed to save {}: {}", path, err),
+        }
+    });
 }
 
 /// Different modes that represent how or whether the sim is updated
@@ -63,7 +223,46 @@ fn pick_window_dims(min: DVec2, max: DVec2) -> UVec2 {
 enum SimState {
     Paused,
     Running,
-    RunOneFrame,
+    /// Run this many steps, and then pause
+    RunSteps(u32),
+}
+
+/// How many steps the GPU takes per frame while running
+#[cfg(feature = "wgpu")]
+const GPU_STEPS_PER_FRAME: u32 = 256;
+
+/// Step `gpu` like the sim would be stepped in `state`, and draw it into `fb` if anything changed
+///
+/// `reseeded` means the GPU started over this frame, so there's something new to draw even if it
+/// doesn't step.
+#[cfg(feature = "wgpu")]
+fn run_gpu(
+    gpu: &mut fractal::GpuSim,
+    state: &mut SimState,
+    total_steps: &mut u64,
+    fb: &mut [u32],
+    reseeded: bool,
+) {
+    let steps = match *state {
+        SimState::Paused => 0,
+        SimState::Running => GPU_STEPS_PER_FRAME,
+        SimState::RunSteps(steps) => steps,
+    };
+
+    if steps > 0 {
+        let stats = gpu.update_n(steps);
+        *total_steps += steps as u64;
+
+        // Stop burning the GPU once nothing is going to change anymore
+        if stats.active == 0 && matches!(state, SimState::Running) {
+            println!("Converged after {} steps, pausing", total_steps);
+            *state = SimState::Paused;
+        }
+    }
+
+    if steps > 0 || reseeded {
+        gpu.draw(fb);
+    }
 }
 
 fn main() {
@@ -73,7 +272,7 @@ fn main() {
     // "The" Mandelbrot View
     let (frame_min, frame_max) = make_default_frame();
     let window_dims = pick_window_dims(frame_min, frame_max);
-    let fb_dims = window_dims;
+    let mut fb_dims = window_dims;
 
     let mut window = Window::new(
         &format!("Mandelbrot - {}x{}", window_dims.x, window_dims.y),
@@ -95,18 +294,80 @@ fn main() {
     window.set_key_repeat_delay(0.2);
     window.set_key_repeat_rate(0.2);
 
-    let mut sim = Sim::new(SimConfig {
-        fb_dims,
-        frame_min,
-        frame_max,
-    });
+    let mut sim = Sim::new(
+        SimConfig::builder()
+            .fb_dims(fb_dims.x, fb_dims.y)
+            .build()
+            .expect("Expected a non-empty window"),
+    );
 
     let mut framebuffer: Vec<u32> = vec![0; (fb_dims.x * fb_dims.y) as usize];
 
+    // With --gpu, views that the GPU can run are iterated and drawn there instead, with the plain
+    // palette. The sim still keeps track of the view.
+    #[cfg(feature = "wgpu")]
+    let mut gpu = if std::env::args().any(|arg| arg == "--gpu") {
+        let gpu = fractal::GpuSim::new(*sim.config());
+        if gpu.is_none() {
+            println!("No GPU to run on, iterating on the CPU instead");
+        }
+        gpu
+    } else {
+        None
+    };
+
     let mut frame = 0;
+    let mut title = String::new();
     let mut state = SimState::Running;
     let mut palette_idx = 0;
 
+    // How many steps to take at once with Shift + Right Arrow
+    let mut super_step = 10;
+
+    // How many times the sim has been updated since it was last reset
+    let mut total_steps: u64 = 0;
+
+    // Whether each mouse button was down last frame, so we only zoom once per click
+    let mut left_was_down = false;
+    let mut right_was_down = false;
+
+    // Where the left button went down, while it's held to drag out a selection
+    let mut drag_start: Option<DVec2> = None;
+
+    // Window size we're waiting to settle before resizing the sim to match
+    let mut pending_resize: Option<(UVec2, Instant)> = None;
+
+    // Click zooms animate over several frames instead of jumping straight there
+    let mut zoom_animation: Option<ZoomAnimation> = None;
+
+    // Views we've moved away from, most recent last, for Backspace to go back to
+    let mut history: Vec<SimConfig> = vec![];
+
+    // Views saved with Shift and a number key, which survive restarts with the serde feature
+    #[cfg(feature = "serde")]
+    let mut bookmarks: Vec<Option<SimConfig>> = match fractal::load_bookmarks(BOOKMARKS_PATH) {
+        Ok(bookmarks) => bookmarks,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => vec![],
+        Err(err) => {
+            println!("Failed to load {}: {}", BOOKMARKS_PATH, err);
+            vec![]
+        }
+    };
+    #[cfg(not(feature = "serde"))]
+    let mut bookmarks: Vec<Option<SimConfig>> = vec![];
+    bookmarks.resize(BOOKMARK_KEYS.len(), None);
+    #[cfg(feature = "serde")]
+    let mut bookmarks_changed = false;
+
+    // Remember where we were before moving the view, dropping the oldest views once there are
+    // too many
+    let remember = |history: &mut Vec<SimConfig>, config: SimConfig| {
+        if history.len() >= HISTORY_LEN {
+            history.remove(0);
+        }
+        history.push(config);
+    };
+
     while window.is_open() {
         frame += 1;
         let _frame = frame;
@@ -116,9 +377,148 @@ fn main() {
             break;
         }
 
+        // Match the sim to the window once the window has stopped changing size for a moment.
+        // While it's still changing, minifb stretches our old framebuffer to fit.
+        const RESIZE_DEBOUNCE: Duration = Duration::from_millis(250);
+        let (width, height) = window.get_size();
+        let window_size = UVec2::new(width as u32, height as u32);
+
+        if window_size == fb_dims || width == 0 || height == 0 {
+            pending_resize = None;
+        } else {
+            match pending_resize {
+                Some((size, since)) if size == window_size => {
+                    if since.elapsed() >= RESIZE_DEBOUNCE {
+                        fb_dims = window_size;
+
+                        // Keep the frame's height, and widen or narrow it to match the window
+                        let config = *sim.config();
+                        let center = 0.5 * (config.frame_min + config.frame_max);
+                        let half_height = 0.5 * (config.frame_max.y - config.frame_min.y);
+                        let ratio = fb_dims.x as f64 / fb_dims.y as f64;
+                        let half = DVec2::new(half_height * ratio, half_height);
+                        sim.resize_to_frame(fb_dims, center - half, center + half);
+                        framebuffer = vec![0; (fb_dims.x * fb_dims.y) as usize];
+
+                        zoom_animation = None;
+                        total_steps = 0;
+                        state = SimState::Running;
+                        pending_resize = None;
+                    }
+                }
+                _ => pending_resize = Some((window_size, Instant::now())),
+            }
+        }
+
         // Reset the simulation state
         if window.is_key_down(Key::R) {
             sim.reset();
+            total_steps = 0;
+            state = SimState::Running;
+        }
+
+        // Dragging with the left button selects a region to zoom to, and a left-click without
+        // dragging zooms in on the clicked point. Right-click zooms back out around it.
+        let left_down = window.get_mouse_down(MouseButton::Left);
+        let right_down = window.get_mouse_down(MouseButton::Right);
+
+        // The window may be stretched while a resize settles, so map back into the framebuffer
+        let to_fb = |(x, y): (f32, f32)| {
+            DVec2::new(
+                x as f64 * fb_dims.x as f64 / window_size.x as f64,
+                y as f64 * fb_dims.y as f64 / window_size.y as f64,
+            )
+        };
+        let mouse = window.get_mouse_pos(MouseMode::Clamp).map(to_fb);
+        let mouse_inside = window.get_mouse_pos(MouseMode::Discard).map(to_fb);
+
+        let mut target: Option<(DVec2, DVec2)> = None;
+        let config = *sim.config();
+        let click_zoom = |p: DVec2, zoom: f64| {
+            let (x, y) = (p.x as u32, p.y as u32);
+            (x < fb_dims.x && y < fb_dims.y).then(|| {
+                let center = config.idx_to_complex(x + y * fb_dims.x);
+                zoom_frame(&config, DVec2::new(center.re, center.im), zoom)
+            })
+        };
+
+        if left_down && !left_was_down {
+            drag_start = mouse_inside;
+        } else if !left_down && left_was_down {
+            if let (Some(start), Some(end)) = (drag_start.take(), mouse) {
+                let d = end - start;
+                target = if d.x.abs() < DRAG_THRESHOLD && d.y.abs() < DRAG_THRESHOLD {
+                    click_zoom(start, CLICK_ZOOM)
+                } else {
+                    Some(selection_frame(
+                        &config,
+                        start,
+                        selection_corner(start, end, fb_dims),
+                    ))
+                };
+            }
+        }
+        if right_down && !right_was_down {
+            target = mouse_inside.and_then(|p| click_zoom(p, 1. / CLICK_ZOOM));
+        }
+        left_was_down = left_down;
+        right_was_down = right_down;
+
+        if let Some((min, max)) = target {
+            // Zooming again mid-zoom starts a new zoom from wherever this one has got to, but going
+            // back skips over the frames in between
+            if zoom_animation.is_none() {
+                remember(&mut history, config);
+            }
+            let target = SimConfig {
+                frame_min: min,
+                frame_max: max,
+                ..config
+            };
+            zoom_animation = Some(ZoomAnimation::new(config, target));
+        }
+
+        // Take the next step of an animated zoom. Each step is a new frame, so the sim starts over.
+        if let Some(animation) = &mut zoom_animation {
+            animation.progress += 1;
+            let (min, max) = animation.frame_at(animation.progress as f64 / ZOOM_FRAMES as f64);
+
+            sim.set_frame(min, max);
+            total_steps = 0;
+            state = SimState::Running;
+
+            if animation.progress >= ZOOM_FRAMES {
+                zoom_animation = None;
+            }
+        }
+
+        // Toggle between the Mandelbrot set and a classic Julia set, centering the frame on it
+        if window.is_key_pressed(Key::J, KeyRepeat::No) {
+            let config = *sim.config();
+            let (kind, center) = match config.kind {
+                FractalKind::Mandelbrot => (FractalKind::Julia { c: JULIA_C }, DVec2::zero()),
+                FractalKind::Julia { .. } => {
+                    let (min, max) = make_default_frame();
+                    (FractalKind::Mandelbrot, 0.5 * (min + max))
+                }
+            };
+            let half = 0.5 * (config.frame_max - config.frame_min);
+
+            remember(&mut history, config);
+            sim.set_kind(kind);
+            sim.set_frame(center - half, center + half);
+            zoom_animation = None;
+            total_steps = 0;
+            state = SimState::Running;
+        }
+
+        // Save the current view, so it can be rendered again with `gen --config view.json`
+        #[cfg(feature = "serde")]
+        if window.is_key_pressed(Key::C, KeyRepeat::No) {
+            match sim.config().save_to_path("view.json") {
+                Ok(()) => println!("Saved the current view to view.json"),
+                Err(err) => println!("Failed to save view.json: {}", err),
+            }
         }
 
         // Toggle Pause
@@ -130,39 +530,147 @@ fn main() {
             }
         }
 
-        // Advance one iteration at a time with the Right Arrow key
-        if window.is_key_pressed(Key::Right, KeyRepeat::Yes) {
-            if matches!(state, SimState::Paused) {
-                state = SimState::RunOneFrame;
+        // While paused, advance one iteration at a time with the Right Arrow key,
+        // or `super_step` iterations at a time while holding Shift
+        let paused = matches!(state, SimState::Paused);
+        if window.is_key_pressed(Key::Right, KeyRepeat::Yes) && paused {
+            let shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+            state = SimState::RunSteps(if shift { super_step } else { 1 });
+        }
+
+        // Otherwise, the arrow keys pan the view and +/- zoom around its center
+        if !paused {
+            let config = *sim.config();
+            let size = config.frame_max - config.frame_min;
+            let mut center = 0.5 * (config.frame_min + config.frame_max);
+            let mut zoom = 1.;
+
+            if window.is_key_pressed(Key::Left, KeyRepeat::Yes) {
+                center.x -= PAN_FRACTION * size.x;
+            }
+            if window.is_key_pressed(Key::Right, KeyRepeat::Yes) {
+                center.x += PAN_FRACTION * size.x;
+            }
+            if window.is_key_pressed(Key::Up, KeyRepeat::Yes) {
+                center.y += PAN_FRACTION * size.y;
+            }
+            if window.is_key_pressed(Key::Down, KeyRepeat::Yes) {
+                center.y -= PAN_FRACTION * size.y;
+            }
+            if window.is_key_pressed(Key::Equal, KeyRepeat::Yes)
+                || window.is_key_pressed(Key::NumPadPlus, KeyRepeat::Yes)
+            {
+                zoom *= KEY_ZOOM;
+            }
+            if window.is_key_pressed(Key::Minus, KeyRepeat::Yes)
+                || window.is_key_pressed(Key::NumPadMinus, KeyRepeat::Yes)
+            {
+                zoom /= KEY_ZOOM;
+            }
+
+            if center != 0.5 * (config.frame_min + config.frame_max) || zoom != 1. {
+                let (min, max) = zoom_frame(&config, center, zoom);
+
+                remember(&mut history, config);
+                sim.set_frame(min, max);
+                zoom_animation = None;
+                total_steps = 0;
+                state = SimState::Running;
+            }
+        }
+
+        // Adjust how far Shift + Right Arrow steps with the bracket keys
+        if window.is_key_pressed(Key::LeftBracket, KeyRepeat::Yes) {
+            super_step = (super_step / 2).max(1);
+            println!("Super-step is now {} steps", super_step);
+        } else if window.is_key_pressed(Key::RightBracket, KeyRepeat::Yes) {
+            super_step = super_step.saturating_mul(2);
+            println!("Super-step is now {} steps", super_step);
+        }
+
+        let shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+
+        // P cycles through the palettes, or back through them with Shift held
+        if window.is_key_pressed(Key::P, KeyRepeat::No) {
+            let count = palette::ALL.len();
+            palette_idx = if shift {
+                (palette_idx + count - 1) % count
+            } else {
+                (palette_idx + 1) % count
+            };
+            println!("Palette is now {}", palette::ALL[palette_idx].name);
+            sim.mark_all_dirty();
+        }
+
+        // Shift and a number key bookmarks the view, and the number key alone goes back to it
+        let mut go_to: Option<SimConfig> = None;
+        for (slot, &key) in BOOKMARK_KEYS.iter().enumerate() {
+            if !window.is_key_pressed(key, KeyRepeat::No) {
+                continue;
+            }
+
+            if shift {
+                bookmarks[slot] = Some(*sim.config());
+                #[cfg(feature = "serde")]
+                {
+                    bookmarks_changed = true;
+                }
+                println!("Saved bookmark {}", slot + 1);
+            } else if let Some(config) = bookmarks[slot] {
+                remember(&mut history, *sim.config());
+                go_to = Some(config);
             } else {
-                //
-            }
-        }
-
-        if window.is_key_pressed(Key::Key1, KeyRepeat::No) {
-            palette_idx = 1;
-        } else if window.is_key_pressed(Key::Key2, KeyRepeat::No) {
-            palette_idx = 2;
-        } else if window.is_key_pressed(Key::Key3, KeyRepeat::No) {
-            palette_idx = 3;
-        } else if window.is_key_pressed(Key::Key4, KeyRepeat::No) {
-            palette_idx = 4;
-        } else if window.is_key_pressed(Key::Key5, KeyRepeat::No) {
-            palette_idx = 5;
-        } else if window.is_key_pressed(Key::Key6, KeyRepeat::No) {
-            palette_idx = 6;
-        } else if window.is_key_pressed(Key::Key7, KeyRepeat::No) {
-            palette_idx = 7;
-        } else if window.is_key_pressed(Key::Key8, KeyRepeat::No) {
-            palette_idx = 8;
-        } else if window.is_key_pressed(Key::Key9, KeyRepeat::No) {
-            palette_idx = 9;
-        } else if window.is_key_pressed(Key::Key0, KeyRepeat::No) {
-            palette_idx = 0;
+                println!(
+                    "Bookmark {} is empty, save it with Shift+{}",
+                    slot + 1,
+                    slot + 1
+                );
+            }
+        }
+
+        // Backspace goes back to the view before the last zoom, pan, or bookmark
+        if window.is_key_pressed(Key::Backspace, KeyRepeat::Yes) {
+            match history.pop() {
+                Some(config) => go_to = Some(config),
+                None => println!("Nothing to go back to"),
+            }
+        }
+
+        // Views may have been saved at another size, so only their frame and fractal are kept
+        if let Some(config) = go_to {
+            sim = Sim::new(SimConfig { fb_dims, ..config });
+            zoom_animation = None;
+            total_steps = 0;
+            state = SimState::Running;
         }
 
+        #[cfg(feature = "wgpu")]
+        let on_gpu = match &mut gpu {
+            Some(gpu) if fractal::GpuSim::supports(sim.config()) => {
+                let reseeded = total_steps == 0 || gpu.config() != sim.config();
+                if reseeded {
+                    gpu.set_config(*sim.config());
+                }
+                run_gpu(
+                    gpu,
+                    &mut state,
+                    &mut total_steps,
+                    &mut framebuffer,
+                    reseeded,
+                );
+                true
+            }
+            _ => false,
+        };
+
+        #[cfg(not(feature = "wgpu"))]
+        let on_gpu = false;
+
         // Run (or don't run) the simulation
         match state {
+            _ if on_gpu => {
+                // The GPU already ran
+            }
             SimState::Paused => {
                 // Nothing to do when paused
             }
@@ -173,11 +681,13 @@ fn main() {
                     sim.update();
                     Instant::now() - begin
                 };
+                total_steps += 1;
 
                 let mut left = frame_delay;
                 while left > estimate {
                     let begin = Instant::now();
                     sim.update();
+                    total_steps += 1;
 
                     let dur = Instant::now() - begin;
                     estimate = estimate.max(dur);
@@ -189,42 +699,77 @@ fn main() {
                         break;
                     }
                 }
+
+                // Stop burning CPU once nothing is going to change anymore
+                if sim.is_done() {
+                    println!("Converged after {} steps, pausing", total_steps);
+                    state = SimState::Paused;
+                }
             }
-            SimState::RunOneFrame => {
-                // Time and run a single frame
+            SimState::RunSteps(steps) => {
+                // Time and run our steps
                 let begin = Instant::now();
-                sim.update();
+                sim.update_n(steps);
                 let dur = Instant::now() - begin;
+                total_steps += steps as u64;
 
-                println!("sim.update() took {:?}", dur);
+                println!(
+                    "sim.update_n({}) took {:?} ({} steps total)",
+                    steps, dur, total_steps
+                );
             }
         }
 
-        // Re-draw on the framebuffer unconditionally
+        // Re-draw whatever changed since the last frame, which is nothing once the sim settles
 
-        const PALETTES: [for<'r> fn(&'r fractal::GridCell) -> ultraviolet::DVec3; 5] = [
-            palette::with_plain_colors,
-            palette::with_smooth_stripes,
-            palette::with_lambert_and_colors,
-            palette::with_white_lambert,
-            palette::with_color_from_dz,
-        ];
-
-        if palette_idx >= PALETTES.len() {
-            palette_idx = 0;
+        if !on_gpu {
+            sim.draw_dirty(&mut framebuffer, &palette::ALL[palette_idx].color);
         }
 
-        sim.draw(&mut framebuffer, PALETTES[palette_idx]);
+        // Save what's on screen, named so the view can be found again
+        if window.is_key_pressed(Key::S, KeyRepeat::No) {
+            screenshot(&framebuffer, sim.config(), palette::ALL[palette_idx].name);
+        }
 
-        // If we stepped a single frame this loop, reset our state to Paused
+        // If we stepped manually this loop, reset our state to Paused
         // Otherwise, we'll keep updating!
-        if matches!(state, SimState::RunOneFrame) {
+        if matches!(state, SimState::RunSteps(_)) {
             state = SimState::Paused;
         }
 
+        // Show the region that a drag will zoom to, over the top of everything else
+        let selection = drag_start.zip(mouse).and_then(|(start, end)| {
+            let d = end - start;
+            (d.x.abs() >= DRAG_THRESHOLD || d.y.abs() >= DRAG_THRESHOLD)
+                .then(|| (start, selection_corner(start, end, fb_dims)))
+        });
+        if let Some((start, corner)) = selection {
+            draw_selection(&mut framebuffer, fb_dims, start, corner);
+        }
+
+        // Only touch the title when it changes, since that goes through the window system
+        let new_title = window_title(sim.config(), total_steps);
+        if new_title != title {
+            window.set_title(&new_title);
+            title = new_title;
+        }
+
         // Update the framebuffer unconditionally
         window
             .update_with_buffer(&framebuffer, fb_dims.x as usize, fb_dims.y as usize)
             .unwrap();
+
+        // Inverting twice puts the pixels back, so the next `draw_dirty` starts from a clean frame
+        if let Some((start, corner)) = selection {
+            draw_selection(&mut framebuffer, fb_dims, start, corner);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    if bookmarks_changed {
+        match fractal::save_bookmarks(BOOKMARKS_PATH, &bookmarks) {
+            Ok(()) => println!("Saved bookmarks to {}", BOOKMARKS_PATH),
+            Err(err) => println!("Failed to save {}: {}", BOOKMARKS_PATH, err),
+        }
     }
 }
@@ -0,0 +1,143 @@
+use ultraviolet::DVec2;
+
+use std::time::{Duration, Instant};
+
+use fractal::{make_default_frame, palette, Sim, SimConfig, DEFAULT_MAX_ITERS};
+
+/// Parse the value following `--name` on the command line, if it's there
+fn arg_value<T: std::str::FromStr>(name: &str) -> Option<T> {
+    std::env::args()
+        .skip_while(|arg| arg != name)
+        .nth(1)
+        .map(|value| {
+            value
+                .parse()
+                .unwrap_or_else(|_| panic!("Couldn't parse {:?} after {}", value, name))
+        })
+}
+
+/// Render a sequence of frames zooming in on a point, as numbered PNGs
+///
+/// Stitch them into a video with something like
+/// `ffmpeg -framerate 30 -i zoom-%04d.png -pix_fmt yuv420p zoom.mp4`.
+fn main() {
+    // Deep in Seahorse Valley, which stays interesting for a long way down
+    let center = DVec2::new(
+        arg_value("--center-x").unwrap_or(-0.743_643_887_037_151),
+        arg_value("--center-y").unwrap_or(0.131_825_904_205_330),
+    );
+
+    let frames: u32 = arg_value("--frames").unwrap_or(120);
+
+    // Every frame's radius is this much of the last one's
+    let factor: f64 = arg_value("--factor").unwrap_or(0.95);
+
+    // Iterate relative to a reference orbit, which stays sharp on deep zooms
+    let perturb = std::env::args().any(|arg| arg == "--perturb");
+
+    // Blur each frame over this many renders between it and the next, for motion blur
+    let subframes: u32 = arg_value("--subframes").unwrap_or(1).max(1);
+    assert!(
+        subframes == 1 || !perturb,
+        "--perturb doesn't apply to the subframes of --subframes"
+    );
+
+    let (frame_min, frame_max) = make_default_frame();
+    let aspect_ratio = (frame_max.x - frame_min.x) / (frame_max.y - frame_min.y);
+    let start_radius: f64 = arg_value("--radius").unwrap_or(0.5 * (frame_max.x - frame_min.x));
+
+    let width: u32 = arg_value("--width").unwrap_or(640);
+    let height: u32 = arg_value("--height").unwrap_or((width as f64 / aspect_ratio) as u32);
+    let max_iters: u32 = arg_value("--iters").unwrap_or(DEFAULT_MAX_ITERS);
+
+    let palette_name: String = arg_value("--palette").unwrap_or_else(|| "smooth".to_string());
+    let palette = palette::by_name(&palette_name).unwrap_or_else(|| {
+        panic!(
+            "Unknown palette {:?}, expected one of {:?}",
+            palette_name,
+            palette::names()
+        )
+    });
+
+    let output: String = arg_value("--output").unwrap_or_else(|| "zoom".to_string());
+    std::fs::create_dir_all(&output).expect("Failed to create the output directory");
+
+    let mut frame_times: Vec<Duration> = vec![];
+    let raw_begin = Instant::now();
+
+    let mut radius = start_radius;
+    let mut framebuffer: Vec<u32> = vec![];
+    for frame in 0..frames {
+        let begin = Instant::now();
+
+        let config = SimConfig::builder()
+            .fb_dims(width, height)
+            .center(center)
+            .zoom(radius)
+            .max_iters(max_iters)
+            .build()
+            .expect("Expected a non-empty framebuffer");
+        let mut steps = 0;
+        if subframes > 1 {
+            framebuffer = fractal::render_zoom_blurred(&config, factor, subframes, palette);
+        } else {
+            let mut sim = Sim::new(config);
+
+            // Same cap as `gen`: escaped cells only need a handful of steps past `max_iters`
+            let max_steps = config.max_iters.saturating_add(64);
+            let mut active = sim.active_count();
+            while active > 0 && steps < max_steps {
+                steps += 1;
+                active = if perturb {
+                    sim.update_perturbed().active
+                } else {
+                    sim.update().active
+                };
+            }
+
+            framebuffer.resize((width * height) as usize, 0);
+            sim.draw(&mut framebuffer, &palette);
+        }
+
+        let filename = format!("{}/zoom-{:04}.png", output, frame);
+        fractal::save_png(&filename, &framebuffer, &config, &palette_name)
+            .expect("Failed to save image");
+
+        let elapsed = Instant::now() - begin;
+        frame_times.push(elapsed);
+        let work = if subframes > 1 {
+            format!("{} subframes", subframes)
+        } else {
+            format!("{} steps", steps)
+        };
+        println!(
+            "Frame {}/{}: radius {:.3e}, {}, {:?}",
+            frame + 1,
+            frames,
+            radius,
+            work,
+            elapsed
+        );
+
+        radius *= factor;
+    }
+
+    let raw_end = Instant::now();
+
+    // Print stats
+
+    let wall = raw_end - raw_begin;
+    let sum: Duration = frame_times.iter().sum();
+    let ave = {
+        let ns = sum.as_nanos() as f64;
+        let ave = ns / frame_times.len().max(1) as f64;
+
+        Duration::from_nanos(ave as u64)
+    };
+    let slowest = frame_times.iter().max().copied().unwrap_or_default();
+
+    dbg!(wall);
+    dbg!(frames);
+    dbg!(ave);
+    dbg!(slowest);
+}
@@ -0,0 +1,272 @@
+//! Rendering the Buddhabrot
+//!
+//! Instead of coloring each pixel by how its own point behaves, the Buddhabrot samples random `c`
+//! and counts every pixel that an escaping orbit passes through. This is a separate pipeline from
+//! `Sim`, sharing only the coordinate mapping of `SimConfig`.
+
+use num::Complex;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use ultraviolet::{DVec2, DVec3};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::{compute_orbit, encode, SimConfig};
+
+/// Corners of the region that `c` is sampled from
+///
+/// Every `c` outside of the radius 2 disk escapes immediately, so there's nothing to gain from
+/// sampling further out.
+const SAMPLE_MIN: DVec2 = DVec2::new(-2., -2.);
+const SAMPLE_MAX: DVec2 = DVec2::new(2., 2.);
+
+/// How random `c` values are picked for the Buddhabrot
+///
+/// Every strategy is unbiased. `Stratified` spreads the same number of samples more evenly, so it
+/// gives less noisy images than `Uniform`. `Antithetic` is about as noisy per sample, but only
+/// iterates half of them, and its image is exactly symmetric.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BuddhabrotSampling {
+    /// Each sample is independent and uniform over the sampling region
+    #[default]
+    Uniform,
+
+    /// The sampling region is split into a `cells` by `cells` grid, and consecutive samples take
+    /// turns in each cell, jittered uniformly within it
+    Stratified { cells: u32 },
+
+    /// Samples come in pairs: a uniform `c` and its reflection across the real axis
+    ///
+    /// The set is symmetric across the real axis, so the mirrored orbit is just the conjugate of the
+    /// original, and doesn't need to be iterated again.
+    Antithetic,
+}
+
+/// How many consecutive draws share one seeded RNG
+///
+/// Each chunk's RNG is seeded from the render's seed and the chunk's index, so the same seed gives
+/// the same image no matter how the chunks are split across threads.
+const DRAWS_PER_RNG: u32 = 4096;
+
+/// Seed for the RNG of chunk `chunk` of a render seeded with `seed`
+///
+/// Both are mixed through SplitMix64, so nearby seeds and chunks get unrelated streams. Adding them
+/// would make chunk `k` of seed `s` the same stream as chunk `k - 1` of seed `s + 1`.
+fn chunk_seed(seed: u64, chunk: u32) -> u64 {
+    splitmix64(splitmix64(seed) ^ chunk as u64)
+}
+
+/// One step of the SplitMix64 generator, used here as a hash
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Render the Buddhabrot density of `samples` uniformly random `c`s, following each for up to
+/// `max_iters`
+///
+/// Returns one count per pixel of `config.fb_dims`, of how many escaping orbits passed through it.
+/// Only the frame and dimensions of `config` are used, and the same `seed` always gives the same
+/// counts. See `render_with_sampling` to pick how `c`s are sampled, and `tone_map` to turn the
+/// counts into an image.
+pub fn render(config: SimConfig, samples: u32, max_iters: u32, seed: u64) -> Vec<u32> {
+    render_with_sampling(
+        config,
+        samples,
+        max_iters,
+        BuddhabrotSampling::Uniform,
+        seed,
+    )
+}
+
+/// Render the Buddhabrot density of `samples` random `c`s, following each for up to `max_iters`
+///
+/// Returns one count per pixel of `config.fb_dims`, of how many escaping orbits passed through it.
+/// Only the frame and dimensions of `config` are used, and the same `seed` always gives the same
+/// counts. With `BuddhabrotSampling::Antithetic`, an odd number of `samples` is rounded up to the
+/// next pair.
+pub fn render_with_sampling(
+    config: SimConfig,
+    samples: u32,
+    max_iters: u32,
+    sampling: BuddhabrotSampling,
+    seed: u64,
+) -> Vec<u32> {
+    let size = (config.fb_dims.x * config.fb_dims.y) as usize;
+    let extent = SAMPLE_MAX - SAMPLE_MIN;
+
+    let draws = match sampling {
+        BuddhabrotSampling::Uniform => samples,
+        BuddhabrotSampling::Stratified { cells } => {
+            assert!(cells > 0, "Expected at least one stratum");
+            samples
+        }
+        BuddhabrotSampling::Antithetic => samples.div_ceil(2),
+    };
+
+    let accumulate = |density: &mut Vec<u32>, i: u32, rng: &mut StdRng| {
+        let jitter = DVec2::new(rng.gen(), rng.gen());
+        let p = match sampling {
+            BuddhabrotSampling::Uniform | BuddhabrotSampling::Antithetic => {
+                SAMPLE_MIN + jitter * extent
+            }
+            BuddhabrotSampling::Stratified { cells } => {
+                let stratum = i % (cells * cells);
+                let cell = DVec2::new((stratum % cells) as f64, (stratum / cells) as f64);
+                SAMPLE_MIN + (cell + jitter) / cells as f64 * extent
+            }
+        };
+
+        let orbit = compute_orbit(Complex::new(p.x, p.y), max_iters);
+        let escaped = orbit.last().is_some_and(|z| z.norm_sqr() > 4.0);
+        if !escaped {
+            return;
+        }
+
+        let mirror = sampling == BuddhabrotSampling::Antithetic;
+        for z in orbit {
+            if let Some(p) = config.complex_to_pixel(z) {
+                density[(p.y * config.fb_dims.x + p.x) as usize] += 1;
+            }
+            if mirror {
+                if let Some(p) = config.complex_to_pixel(z.conj()) {
+                    density[(p.y * config.fb_dims.x + p.x) as usize] += 1;
+                }
+            }
+        }
+    };
+
+    let accumulate_chunk = |density: &mut Vec<u32>, chunk: u32| {
+        let mut rng = StdRng::seed_from_u64(chunk_seed(seed, chunk));
+        let first = chunk * DRAWS_PER_RNG;
+        for i in first..draws.min(first.saturating_add(DRAWS_PER_RNG)) {
+            accumulate(density, i, &mut rng);
+        }
+    };
+    let chunks = draws.div_ceil(DRAWS_PER_RNG);
+
+    #[cfg(feature = "rayon")]
+    {
+        (0..chunks)
+            .into_par_iter()
+            .fold(
+                || vec![0; size],
+                |mut density, chunk| {
+                    accumulate_chunk(&mut density, chunk);
+                    density
+                },
+            )
+            .reduce(
+                || vec![0; size],
+                |mut a, b| {
+                    for (a, b) in a.iter_mut().zip(b) {
+                        *a += b;
+                    }
+                    a
+                },
+            )
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    {
+        let mut density = vec![0; size];
+        for chunk in 0..chunks {
+            accumulate_chunk(&mut density, chunk);
+        }
+        density
+    }
+}
+
+/// Turn the counts from `render` into a grayscale framebuffer, with the same layout
+///
+/// Counts span several orders of magnitude, with a few pixels near the real axis far brighter than
+/// the rest, so they're scaled by their logarithm: the brightest pixel is white, and unvisited
+/// pixels are black.
+pub fn tone_map(density: &[u32]) -> Vec<u32> {
+    let max = density.iter().copied().max().unwrap_or(0);
+    let scale = 1. / (max as f64).ln_1p().max(f64::MIN_POSITIVE);
+
+    density
+        .iter()
+        .map(|&count| encode(DVec3::broadcast((count as f64).ln_1p() * scale)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_config() -> SimConfig {
+        SimConfig::builder()
+            .fb_dims(16, 16)
+            .build()
+            .expect("Expected a non-empty framebuffer")
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_counts() {
+        let config = small_config();
+        let render = |seed| render(config, 10_000, 50, seed);
+
+        assert_eq!(render(7), render(7));
+        assert_ne!(render(7), render(8));
+    }
+
+    #[test]
+    fn nearby_seeds_share_no_chunks() {
+        let seeds: Vec<u64> = (0..4)
+            .flat_map(|seed| (0..4).map(move |chunk| chunk_seed(seed, chunk)))
+            .collect();
+
+        let mut unique = seeds.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), seeds.len());
+    }
+
+    #[test]
+    fn sampling_noise_at_a_fixed_sample_count() {
+        const SEEDS: u64 = 16;
+        const SAMPLES: u32 = 16_384;
+        let config = small_config();
+
+        // Variance of each pixel's count across seeds, summed over the image, relative to the
+        // squared mean of the total count. Antithetic counts each orbit twice, and this doesn't
+        // depend on the scale of the counts.
+        let noise = |sampling, samples| {
+            let renders: Vec<Vec<u32>> = (0..SEEDS)
+                .map(|seed| render_with_sampling(config, samples, 50, sampling, seed))
+                .collect();
+
+            let mut variance = 0.;
+            let mut total = 0.;
+            for i in 0..renders[0].len() {
+                let counts = renders.iter().map(|density| density[i] as f64);
+                let mean = counts.clone().sum::<f64>() / SEEDS as f64;
+                variance +=
+                    counts.map(|count| (count - mean).powi(2)).sum::<f64>() / (SEEDS - 1) as f64;
+                total += mean;
+            }
+            variance / (total * total)
+        };
+
+        let uniform = noise(BuddhabrotSampling::Uniform, SAMPLES);
+        let stratified = noise(BuddhabrotSampling::Stratified { cells: 64 }, SAMPLES);
+        let antithetic = noise(BuddhabrotSampling::Antithetic, SAMPLES);
+        let uniform_half = noise(BuddhabrotSampling::Uniform, SAMPLES / 2);
+
+        // Stratifying cuts the noise to about half
+        assert!(stratified < 0.75 * uniform, "{} vs {}", stratified, uniform);
+
+        // Antithetic pairs are about as noisy per sample, and much less noisy per orbit iterated
+        assert!(antithetic < 1.6 * uniform, "{} vs {}", antithetic, uniform);
+        assert!(
+            antithetic < 0.8 * uniform_half,
+            "{} vs {}",
+            antithetic,
+            uniform_half
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Building a `SimConfig` from a center and zoom
+
+use std::fmt;
+
+use ultraviolet::{DVec2, UVec2};
+
+use crate::{
+    make_default_frame, CoordTransform, Formula, FractalKind, SimConfig, DEFAULT_MAX_ITERS,
+    DEFAULT_STEP_RADIUS_SQR,
+};
+
+/// Why a `SimConfigBuilder` couldn't build a `SimConfig`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildError {
+    /// `fb_dims` was never set
+    MissingDims,
+
+    /// `fb_dims` has a zero width or height
+    ZeroDims,
+
+    /// `samples_per_axis` is zero
+    ZeroSamples,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::MissingDims => write!(f, "framebuffer dimensions were never set"),
+            BuildError::ZeroDims => write!(f, "framebuffer dimensions must be non-zero"),
+            BuildError::ZeroSamples => write!(f, "there must be at least one sample per axis"),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/// Builds a `SimConfig` from a center and zoom, instead of the corners of the frame
+///
+/// Everything but `fb_dims` has a default, which matches `make_default_frame` and the defaults of
+/// `SimConfig`'s other fields. Make one with `SimConfig::builder`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimConfigBuilder {
+    fb_dims: Option<UVec2>,
+    center: DVec2,
+    zoom: f64,
+    transform: CoordTransform,
+    kind: FractalKind,
+    max_iters: u32,
+    power: u32,
+    formula: Formula,
+    samples_per_axis: u32,
+    step_radius_sqr: f64,
+}
+
+impl SimConfig {
+    /// Start building a config, e.g. `SimConfig::builder().fb_dims(w, h).center(c).zoom(z).build()`
+    pub fn builder() -> SimConfigBuilder {
+        let (min, max) = make_default_frame();
+
+        SimConfigBuilder {
+            fb_dims: None,
+            center: 0.5 * (min + max),
+            zoom: 0.5 * (max.x - min.x),
+            transform: CoordTransform::Identity,
+            kind: FractalKind::Mandelbrot,
+            max_iters: DEFAULT_MAX_ITERS,
+            power: 2,
+            formula: Formula::Standard,
+            samples_per_axis: 1,
+            step_radius_sqr: DEFAULT_STEP_RADIUS_SQR,
+        }
+    }
+}
+
+impl SimConfigBuilder {
+    pub fn fb_dims(mut self, width: u32, height: u32) -> Self {
+        self.fb_dims = Some(UVec2::new(width, height));
+        self
+    }
+
+    /// Point of the complex plane at the center of the frame
+    pub fn center(mut self, center: DVec2) -> Self {
+        self.center = center;
+        self
+    }
+
+    /// Half of the width of the frame, in complex-plane units
+    ///
+    /// The height follows from the aspect ratio of `fb_dims`, so circles stay circular.
+    pub fn zoom(mut self, half_width: f64) -> Self {
+        self.zoom = half_width;
+        self
+    }
+
+    pub fn transform(mut self, transform: CoordTransform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn kind(mut self, kind: FractalKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn max_iters(mut self, max_iters: u32) -> Self {
+        self.max_iters = max_iters;
+        self
+    }
+
+    pub fn power(mut self, power: u32) -> Self {
+        self.power = power;
+        self
+    }
+
+    pub fn formula(mut self, formula: Formula) -> Self {
+        self.formula = formula;
+        self
+    }
+
+    /// Supersample each pixel with a `samples` by `samples` block of cells
+    pub fn samples_per_axis(mut self, samples: u32) -> Self {
+        self.samples_per_axis = samples;
+        self
+    }
+
+    /// Keep stepping escaped cells until `|z|^2` passes this
+    pub fn step_radius_sqr(mut self, radius_sqr: f64) -> Self {
+        self.step_radius_sqr = radius_sqr;
+        self
+    }
+
+    pub fn build(self) -> Result<SimConfig, BuildError> {
+        let fb_dims = self.fb_dims.ok_or(BuildError::MissingDims)?;
+        if fb_dims.x == 0 || fb_dims.y == 0 {
+            return Err(BuildError::ZeroDims);
+        }
+        if self.samples_per_axis == 0 {
+            return Err(BuildError::ZeroSamples);
+        }
+
+        let ratio = fb_dims.x as f64 / fb_dims.y as f64;
+        let half = DVec2::new(self.zoom, self.zoom / ratio);
+
+        Ok(SimConfig {
+            fb_dims,
+            frame_min: self.center - half,
+            frame_max: self.center + half,
+            transform: self.transform,
+            kind: self.kind,
+            max_iters: self.max_iters,
+            power: self.power,
+            formula: self.formula,
+            samples_per_axis: self.samples_per_axis,
+            step_radius_sqr: self.step_radius_sqr,
+        })
+    }
+}
@@ -0,0 +1,513 @@
+//! Saving rendered framebuffers, and the configs that made them, to disk
+
+#[cfg(feature = "serde")]
+use std::fs;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+#[cfg(feature = "image")]
+use num::Complex;
+use ultraviolet::DVec3;
+#[cfg(feature = "image")]
+use ultraviolet::{DVec2, UVec2};
+
+#[cfg(any(feature = "image", feature = "serde"))]
+use crate::SimConfig;
+#[cfg(feature = "image")]
+use crate::{CoordTransform, Formula, FractalKind, DEFAULT_MAX_ITERS, DEFAULT_STEP_RADIUS_SQR};
+use crate::{GridCell, Sim};
+
+/// Prefix for the PNG text chunk keywords that we write
+#[cfg(feature = "image")]
+const KEYWORD_PREFIX: &str = "fractal.";
+
+/// Change format from 0RGB -> to RGBA, both 8-bit channels
+///
+/// We'll always use 0xFF for alpha.
+pub(crate) fn to_rgba(fb: &[u32]) -> Vec<u8> {
+    const A: u8 = 0xff;
+
+    let mut rgba = Vec::with_capacity(4 * fb.len());
+    for px in fb {
+        // Each pixel is encoded as 0RGB
+        let [z, r, g, b] = px.to_be_bytes();
+        assert_eq!(z, 0);
+
+        // Re-encode as RGBA
+        rgba.extend_from_slice(&[r, g, b, A]);
+    }
+
+    rgba
+}
+
+/// Save a framebuffer of `dims` as a PNG
+#[cfg(feature = "image")]
+pub fn save_framebuffer_png(path: impl AsRef<Path>, fb: &[u32], dims: UVec2) -> io::Result<()> {
+    assert_eq!(fb.len(), (dims.x * dims.y) as usize);
+    write_png(path, &to_rgba(fb), png::BitDepth::Eight, dims, &[])
+}
+
+/// Save a framebuffer drawn from `config` as a PNG
+///
+/// The frame and the name of the palette used are embedded in the PNG as `tEXt` chunks, so the
+/// config can be recovered later with `read_png_params`.
+#[cfg(feature = "image")]
+pub fn save_png(
+    path: impl AsRef<Path>,
+    fb: &[u32],
+    config: &SimConfig,
+    palette_name: &str,
+) -> io::Result<()> {
+    let metadata = config_metadata(config, palette_name);
+    write_png(
+        path,
+        &to_rgba(fb),
+        png::BitDepth::Eight,
+        config.fb_dims,
+        &metadata,
+    )
+}
+
+/// Save RGBA pixels with 16 bits per channel drawn from `config` as a PNG, like `save_png`
+///
+/// `rgba` is laid out like the output of `Sim::render_rgba16`.
+#[cfg(feature = "image")]
+pub fn save_png16(
+    path: impl AsRef<Path>,
+    rgba: &[u16],
+    config: &SimConfig,
+    palette_name: &str,
+) -> io::Result<()> {
+    // PNG stores 16-bit samples big-endian
+    let bytes: Vec<u8> = rgba.iter().flat_map(|x| x.to_be_bytes()).collect();
+
+    let metadata = config_metadata(config, palette_name);
+    write_png(
+        path,
+        &bytes,
+        png::BitDepth::Sixteen,
+        config.fb_dims,
+        &metadata,
+    )
+}
+
+/// Render `config` in tiles of `tile_dims` with `color`, and stream them into a PNG like `save_png`
+///
+/// This uses `Sim::render_tiled_by_rows`, so the grid never exists all at once. Finished tiles are kept
+/// until their whole band of rows is done, then written out, so memory use is about
+/// `config.fb_dims.x * tile_dims.y` pixels.
+#[cfg(feature = "image")]
+pub fn save_png_tiled<ColorFn>(
+    path: impl AsRef<Path>,
+    config: &SimConfig,
+    tile_dims: UVec2,
+    color: ColorFn,
+    palette_name: &str,
+) -> io::Result<()>
+where
+    ColorFn: Fn(&GridCell) -> DVec3 + Sync,
+{
+    let dims = config.fb_dims;
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, dims.x, dims.y);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    for (key, value) in config_metadata(config, palette_name) {
+        encoder.add_text_chunk(format!("{}{}", KEYWORD_PREFIX, key), value)?;
+    }
+
+    let mut writer = encoder.write_header()?;
+    let mut stream = writer.stream_writer()?;
+
+    // `emit` can't fail, so hold on to the first error and stop writing after it
+    let mut result = Ok(());
+    let mut band: Vec<u8> = vec![];
+    let row_bytes = 4 * dims.x as usize;
+
+    Sim::render_tiled_by_rows(config, tile_dims, color, |origin, tile_dims, pixels| {
+        if result.is_err() {
+            return;
+        }
+
+        band.resize(row_bytes * tile_dims.y as usize, 0);
+        for (y, row) in pixels.chunks_exact(tile_dims.x as usize).enumerate() {
+            let start = y * row_bytes + 4 * origin.x as usize;
+            band[start..start + 4 * row.len()].copy_from_slice(&to_rgba(row));
+        }
+
+        // Tiles go left to right, so the band is done once its last tile is in
+        if origin.x + tile_dims.x == dims.x {
+            result = stream.write_all(&band);
+        }
+    });
+
+    result?;
+    stream.finish()?;
+
+    Ok(())
+}
+
+/// The `tEXt` chunks that `save_png` writes for `config`, as `(key, value)` pairs
+#[cfg(feature = "image")]
+fn config_metadata(config: &SimConfig, palette_name: &str) -> Vec<(&'static str, String)> {
+    let dims = config.fb_dims;
+    vec![
+        ("fb_dims", format!("{}x{}", dims.x, dims.y)),
+        (
+            "frame_min",
+            format!("{},{}", config.frame_min.x, config.frame_min.y),
+        ),
+        (
+            "frame_max",
+            format!("{},{}", config.frame_max.x, config.frame_max.y),
+        ),
+        ("palette", palette_name.to_string()),
+        ("transform", format!("{:?}", config.transform)),
+        (
+            "kind",
+            match config.kind {
+                FractalKind::Mandelbrot => "Mandelbrot".to_string(),
+                FractalKind::Julia { c } => format!("Julia {},{}", c.re, c.im),
+            },
+        ),
+        ("max_iters", config.max_iters.to_string()),
+        ("power", config.power.to_string()),
+        ("formula", format!("{:?}", config.formula)),
+        ("samples_per_axis", config.samples_per_axis.to_string()),
+        ("step_radius_sqr", config.step_radius_sqr.to_string()),
+    ]
+}
+
+/// Write RGBA samples of `depth` as a PNG, with a `tEXt` chunk for each `(key, value)` in
+/// `metadata`
+#[cfg(feature = "image")]
+fn write_png(
+    path: impl AsRef<Path>,
+    data: &[u8],
+    depth: png::BitDepth,
+    dims: UVec2,
+    metadata: &[(&str, String)],
+) -> io::Result<()> {
+    let bytes_per_pixel = 4 * (depth as usize).div_ceil(8);
+    assert_eq!(data.len(), bytes_per_pixel * (dims.x * dims.y) as usize);
+
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, dims.x, dims.y);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(depth);
+
+    for (key, value) in metadata.iter() {
+        encoder.add_text_chunk(format!("{}{}", KEYWORD_PREFIX, key), value.clone())?;
+    }
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(data)?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+/// Recover the `SimConfig` that a PNG written by `save_png` was rendered with
+///
+/// Returns `None` if the file can't be read or doesn't carry our metadata.
+#[cfg(feature = "image")]
+pub fn read_png_params(path: impl AsRef<Path>) -> Option<SimConfig> {
+    let decoder = png::Decoder::new(File::open(path).ok()?);
+    let reader = decoder.read_info().ok()?;
+
+    let lookup = |key: &str| -> Option<&str> {
+        reader
+            .info()
+            .uncompressed_latin1_text
+            .iter()
+            .find(|chunk| chunk.keyword.strip_prefix(KEYWORD_PREFIX) == Some(key))
+            .map(|chunk| chunk.text.as_str())
+    };
+
+    let parse_pair = |text: &str, sep: char| -> Option<(f64, f64)> {
+        let (a, b) = text.split_once(sep)?;
+        Some((a.trim().parse().ok()?, b.trim().parse().ok()?))
+    };
+
+    let (w, h) = parse_pair(lookup("fb_dims")?, 'x')?;
+    let (min_x, min_y) = parse_pair(lookup("frame_min")?, ',')?;
+    let (max_x, max_y) = parse_pair(lookup("frame_max")?, ',')?;
+
+    // Older files won't have a transform, and were always rendered without one
+    let transform = match lookup("transform") {
+        None | Some("Identity") => CoordTransform::Identity,
+        Some("Stereographic") => CoordTransform::Stereographic,
+        Some(_) => return None,
+    };
+
+    let kind = match lookup("kind") {
+        None | Some("Mandelbrot") => FractalKind::Mandelbrot,
+        Some(text) => {
+            let (re, im) = parse_pair(text.strip_prefix("Julia ")?, ',')?;
+            FractalKind::Julia {
+                c: Complex::new(re, im),
+            }
+        }
+    };
+
+    // Older files won't have a limit either
+    let max_iters = match lookup("max_iters") {
+        None => DEFAULT_MAX_ITERS,
+        Some(text) => text.parse().ok()?,
+    };
+
+    // ...or a power, and those were always squared
+    let power = match lookup("power") {
+        None => 2,
+        Some(text) => text.parse().ok()?,
+    };
+
+    let formula = match lookup("formula") {
+        None | Some("Standard") => Formula::Standard,
+        Some("BurningShip") => Formula::BurningShip,
+        Some(_) => return None,
+    };
+
+    // ...and weren't supersampled
+    let samples_per_axis = match lookup("samples_per_axis") {
+        None => 1,
+        Some(text) => text.parse().ok()?,
+    };
+
+    // ...and always stepped to the same radius
+    let step_radius_sqr = match lookup("step_radius_sqr") {
+        None => DEFAULT_STEP_RADIUS_SQR,
+        Some(text) => text.parse().ok()?,
+    };
+
+    Some(SimConfig {
+        fb_dims: UVec2::new(w as u32, h as u32),
+        frame_min: DVec2::new(min_x, min_y),
+        frame_max: DVec2::new(max_x, max_y),
+        transform,
+        kind,
+        max_iters,
+        power,
+        formula,
+        samples_per_axis,
+        step_radius_sqr,
+    })
+}
+
+/// The old name of `read_png_params`
+#[cfg(feature = "image")]
+#[deprecated(note = "renamed to `read_png_params`")]
+pub fn load_config_from_png(path: impl AsRef<Path>) -> Option<SimConfig> {
+    read_png_params(path)
+}
+
+impl Sim {
+    /// Draw the grid with `color` and save it as a PNG, like `save_png`
+    ///
+    /// The palette is recorded as `"custom"`, since there's no name to go with an arbitrary `color`.
+    #[cfg(feature = "image")]
+    pub fn save_png<ColorFn>(&self, path: impl AsRef<Path>, color: ColorFn) -> io::Result<()>
+    where
+        ColorFn: Fn(&GridCell) -> DVec3 + Sync,
+    {
+        let dims = self.config.fb_dims;
+        let mut framebuffer: Vec<u32> = vec![0; (dims.x * dims.y) as usize];
+        self.draw(&mut framebuffer, &color);
+
+        save_png(path, &framebuffer, &self.config, "custom")
+    }
+
+    /// Draw the grid with `color` and save it as a binary PPM (`P6`)
+    ///
+    /// PPM is just a short text header and the raw RGB bytes, so this is written with `std::io`
+    /// alone and works without the `image` feature. Most image viewers and tools can open it.
+    pub fn save_ppm<ColorFn>(&self, path: impl AsRef<Path>, color: ColorFn) -> io::Result<()>
+    where
+        ColorFn: Fn(&GridCell) -> DVec3 + Sync,
+    {
+        let dims = self.config.fb_dims;
+        let mut framebuffer: Vec<u32> = vec![0; (dims.x * dims.y) as usize];
+        self.draw(&mut framebuffer, &color);
+
+        let mut out = BufWriter::new(File::create(path)?);
+        write!(out, "P6\n{} {}\n255\n", dims.x, dims.y)?;
+        for px in framebuffer {
+            // Each pixel is encoded as 0RGB
+            let [_, r, g, b] = px.to_be_bytes();
+            out.write_all(&[r, g, b])?;
+        }
+
+        out.flush()
+    }
+
+    /// Export the grid as a triangulated heightmap mesh in Wavefront OBJ format
+    ///
+    /// There's one vertex per cell, at `(re, im, scale * height_fn(cell))` of the cell's pixel, and
+    /// two triangles for every square of four neighboring cells, facing `+z`. For example, a height function that
+    /// returns the iteration count gives a terrain that climbs towards the set, with the set itself
+    /// as a flat plateau.
+    ///
+    /// The mesh resolution is `grid_dims`, and the file is written as text, so expect roughly 100
+    /// bytes per cell: around 80 MB for a 1080x770 grid.
+    pub fn export_obj(
+        &self,
+        path: impl AsRef<Path>,
+        height_fn: impl Fn(&GridCell) -> f64,
+        scale: f64,
+    ) -> io::Result<()> {
+        let dims = self.config.grid_dims();
+        let mut out = BufWriter::new(File::create(path)?);
+
+        for (idx, cell) in self.grid.iter().enumerate() {
+            let p = self.config.grid_idx_to_complex(idx as u32);
+            let z = scale * height_fn(cell);
+            writeln!(out, "v {} {} {}", p.re, p.im, z)?;
+        }
+
+        // OBJ indices are 1-based
+        let vertex = |x: u32, y: u32| y * dims.x + x + 1;
+        for y in 0..dims.y.saturating_sub(1) {
+            for x in 0..dims.x.saturating_sub(1) {
+                let (a, b) = (vertex(x, y), vertex(x + 1, y));
+                let (c, d) = (vertex(x, y + 1), vertex(x + 1, y + 1));
+
+                // Rows go down the screen, so these wind counter-clockwise when viewed from +z
+                writeln!(out, "f {} {} {}", a, c, b)?;
+                writeln!(out, "f {} {} {}", b, c, d)?;
+            }
+        }
+
+        out.flush()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl SimConfig {
+    /// Write this config to `path` as JSON
+    pub fn save_to_path(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json + "\n")
+    }
+
+    /// Read a config from JSON at `path`, like one written by `save_to_path`
+    pub fn load_from_path(path: impl AsRef<Path>) -> io::Result<SimConfig> {
+        let text = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&text)?)
+    }
+}
+
+/// Write a list of bookmarked configs to `path` as a JSON array, with `null` for empty slots
+#[cfg(feature = "serde")]
+pub fn save_bookmarks(path: impl AsRef<Path>, bookmarks: &[Option<SimConfig>]) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(bookmarks)?;
+    fs::write(path, json + "\n")
+}
+
+/// Read a list of bookmarked configs from JSON at `path`, like one written by `save_bookmarks`
+#[cfg(feature = "serde")]
+pub fn load_bookmarks(path: impl AsRef<Path>) -> io::Result<Vec<Option<SimConfig>>> {
+    let text = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&text)?)
+}
+
+#[cfg(all(test, any(feature = "image", feature = "serde")))]
+mod tests {
+    use super::*;
+    use crate::{CoordTransform, Formula, FractalKind, SimConfig};
+    use num::Complex;
+
+    /// A config that differs from the defaults in every field
+    fn unusual_config() -> SimConfig {
+        SimConfig {
+            fb_dims: ultraviolet::UVec2::new(30, 20),
+            frame_min: ultraviolet::DVec2::new(-0.1 / 3., 0.7),
+            frame_max: ultraviolet::DVec2::new(0.2, 0.8 + 1e-12),
+            kind: FractalKind::Julia {
+                c: Complex::new(-0.8, 0.156),
+            },
+            formula: Formula::BurningShip,
+            transform: CoordTransform::Stereographic,
+            max_iters: 300,
+            power: 3,
+            samples_per_axis: 2,
+            step_radius_sqr: 0.1 + 1e6,
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn png_params_round_trip() {
+        let config = unusual_config();
+        let path = std::env::temp_dir().join(format!("fractal-{}.png", std::process::id()));
+        let pixels = (config.fb_dims.x * config.fb_dims.y) as usize;
+
+        save_png(&path, &vec![0; pixels], &config, "plain").unwrap();
+        assert_eq!(read_png_params(&path), Some(config));
+        #[allow(deprecated)]
+        let old = load_config_from_png(&path);
+        assert_eq!(old, Some(config));
+
+        save_png16(&path, &vec![0; 4 * pixels], &config, "plain").unwrap();
+        assert_eq!(read_png_params(&path), Some(config));
+
+        let small = SimConfig {
+            samples_per_axis: 1,
+            max_iters: 50,
+            ..config
+        };
+        save_png_tiled(
+            &path,
+            &small,
+            ultraviolet::UVec2::new(8, 8),
+            crate::palette::with_plain_colors,
+            "plain",
+        )
+        .unwrap();
+        assert_eq!(read_png_params(&path), Some(small));
+
+        // A PNG without our metadata has no params to read
+        save_framebuffer_png(&path, &vec![0; pixels], config.fb_dims).unwrap();
+        assert_eq!(read_png_params(&path), None);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn configs_and_bookmarks_round_trip_through_json() {
+        let dir = std::env::temp_dir().join(format!("fractal-json-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let config = SimConfig::builder()
+            .fb_dims(320, 200)
+            .center(ultraviolet::DVec2::new(
+                -0.743643887037151,
+                0.131825904205330,
+            ))
+            .zoom(1e-9)
+            .max_iters(5_000)
+            .samples_per_axis(2)
+            .build()
+            .unwrap();
+        let unusual = unusual_config();
+
+        let path = dir.join("view.json");
+        for config in [config, unusual] {
+            config.save_to_path(&path).unwrap();
+            assert_eq!(SimConfig::load_from_path(&path).unwrap(), config);
+        }
+
+        let bookmarks = vec![Some(config), None, Some(unusual)];
+        let path = dir.join("bookmarks.json");
+        save_bookmarks(&path, &bookmarks).unwrap();
+        assert_eq!(load_bookmarks(&path).unwrap(), bookmarks);
+
+        fs::write(&path, "{ not json").unwrap();
+        let err = load_bookmarks(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
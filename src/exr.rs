@@ -0,0 +1,84 @@
+//! Saving unclamped colors as OpenEXR images, with the `exr` crate
+
+use std::io;
+use std::path::Path;
+
+use ultraviolet::DVec3;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::{GridCell, Sim};
+
+impl Sim {
+    /// Draw the grid with `color` and save the raw colors as an OpenEXR image
+    ///
+    /// Unlike `draw`, the colors aren't clamped or quantized, so palettes that go past `1`, like the
+    /// lambert ones, keep their full range for tone mapping later. Supersampled pixels are still
+    /// averaged over their cells. Channels are saved as 32-bit floats.
+    pub fn save_exr<ColorFn>(&self, path: impl AsRef<Path>, color: ColorFn) -> io::Result<()>
+    where
+        ColorFn: Fn(&GridCell) -> DVec3 + Sync,
+    {
+        let pixel = |i: usize| self.pixel_color(i, &color);
+
+        #[cfg(feature = "rayon")]
+        let pixels: Vec<DVec3> = (0..self.fb_len()).into_par_iter().map(pixel).collect();
+
+        #[cfg(not(feature = "rayon"))]
+        let pixels: Vec<DVec3> = (0..self.fb_len()).map(pixel).collect();
+
+        let width = self.config.fb_dims.x as usize;
+        let height = self.config.fb_dims.y as usize;
+        ::exr::prelude::write_rgb_file(path, width, height, |x, y| {
+            let c = pixels[y * width + x];
+            (c.x as f32, c.y as f32, c.z as f32)
+        })
+        .map_err(|err| match err {
+            ::exr::error::Error::Io(err) => err,
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SimConfig;
+    use ::exr::prelude::read_first_rgba_layer_from_file;
+
+    #[test]
+    fn saved_colors_read_back_unclamped() {
+        let config = SimConfig::builder()
+            .fb_dims(24, 16)
+            .build()
+            .expect("Expected a non-empty framebuffer");
+        let mut sim = Sim::new(config);
+        sim.run_until_done(u32::MAX);
+
+        // Past the range `draw` clamps to, and different in every channel
+        let color = |cell: &GridCell| {
+            let t = cell.iters as f64;
+            DVec3::new(t, -0.5 * t, 3.)
+        };
+        let path = std::env::temp_dir().join(format!("fractal-{}.exr", std::process::id()));
+        sim.save_exr(&path, color).unwrap();
+
+        let image = read_first_rgba_layer_from_file(
+            &path,
+            |resolution, _| vec![(0., 0., 0.); resolution.width() * resolution.height()],
+            |pixels, position, (r, g, b, _): (f32, f32, f32, f32)| {
+                pixels[position.y() * 24 + position.x()] = (r, g, b);
+            },
+        )
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let pixels = image.layer_data.channel_data.pixels;
+        assert_eq!(pixels.len(), sim.cells().len());
+        for (pixel, cell) in pixels.iter().zip(sim.cells()) {
+            let c = color(cell);
+            assert_eq!(*pixel, (c.x as f32, c.y as f32, c.z as f32));
+        }
+    }
+}
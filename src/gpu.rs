@@ -0,0 +1,398 @@
+//! Iterating on the GPU with wgpu compute shaders
+//!
+//! `GpuSim` keeps a cell per pixel in a storage buffer, and `gpu.wgsl` steps all of them at once.
+//! Cells iterate in `f32`, so zooms only go about as deep as `Sim::<f32>` before pixels collapse
+//! together (see `precision_divergence`), but every step of a frame is one dispatch instead of a pass
+//! over the grid on the CPU. Only the escape counts come back, and they're colored with the plain
+//! palette.
+
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+use crate::{encode, palette, Formula, GridCell, SimConfig, UpdateStats};
+
+/// Invocations per workgroup, which must match `WORKGROUP_SIZE` in the shader
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Most workgroups a dispatch can have along one axis, in any wgpu backend
+const MAX_GROUPS_PER_AXIS: u32 = 65_535;
+
+/// Values of `GpuCell::state`, which must match the constants in the shader
+///
+/// The shader also has `3` for cells that reached `max_iters`, and treats anything from
+/// `REACHED_BAILOUT` up as done.
+const ITERATING: u32 = 0;
+const ESCAPED: u32 = 1;
+const REACHED_BAILOUT: u32 = 2;
+const INTERIOR: u32 = 4;
+
+/// The shader's `Params`
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Params {
+    len: u32,
+    steps: u32,
+    max_iters: u32,
+    step_radius_sqr: f32,
+}
+
+/// The shader's `Cell`: just enough of a `GridCell` to count iterations
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct GpuCell {
+    z: [f32; 2],
+    c: [f32; 2],
+    iters: u32,
+    state: u32,
+}
+
+impl GpuCell {
+    fn new(cell: &GridCell<f32>) -> Self {
+        Self {
+            z: [cell.z.re, cell.z.im],
+            c: [cell.c.re, cell.c.im],
+            iters: cell.iters,
+            state: if cell.is_interior {
+                INTERIOR
+            } else {
+                ITERATING
+            },
+        }
+    }
+
+    fn has_escaped(&self) -> bool {
+        matches!(self.state, ESCAPED | REACHED_BAILOUT)
+    }
+}
+
+/// A grid of cells for the frame of a `SimConfig`, iterating on the GPU
+///
+/// This mirrors `Sim::new`, `update`, and `draw`, for configs that `GpuSim::supports`. Cells
+/// iterate in `f32` and escape counts match `Sim::<f32>`, except that interior cells aren't caught
+/// by a period check, so they run all the way to `max_iters`.
+pub struct GpuSim {
+    config: SimConfig,
+
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+
+    params: wgpu::Buffer,
+    cells: wgpu::Buffer,
+    counts: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+
+    /// Where `cells` and `counts` are copied to be read back, sized like them
+    cells_readback: wgpu::Buffer,
+    counts_readback: wgpu::Buffer,
+}
+
+impl GpuSim {
+    /// Whether `GpuSim` can iterate `config`
+    ///
+    /// The shader only knows the standard, quadratic formula, with one sample per pixel. Either
+    /// fractal kind and any transform work, since cells are seeded on the CPU.
+    pub fn supports(config: &SimConfig) -> bool {
+        config.formula == Formula::Standard && config.power == 2 && config.samples_per_axis == 1
+    }
+
+    /// Make a sim on the default GPU, or `None` if there isn't one or it can't run `config`
+    pub fn new(config: SimConfig) -> Option<Self> {
+        if !Self::supports(&config) {
+            return None;
+        }
+
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter =
+            pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+                .ok()?;
+
+        // The grid can easily be bigger than the default limit on storage buffers
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("fractal"),
+            required_limits: adapter.limits(),
+            ..Default::default()
+        }))
+        .ok()?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("gpu.wgsl"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("gpu.wgsl"))),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("step"),
+            layout: None,
+            module: &module,
+            entry_point: Some("step"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let params = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("params"),
+            size: std::mem::size_of::<Params>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let counts = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("counts"),
+            size: 8,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let counts_readback = readback_buffer(&device, 8);
+
+        // Placeholders until `seed` makes buffers of the right size
+        let cells = cells_buffer(&device, &[GpuCell::zeroed()]);
+        let cells_readback = readback_buffer(&device, std::mem::size_of::<GpuCell>() as u64);
+        let bind_group = bind_group(&device, &pipeline, &params, &cells, &counts);
+
+        let mut sim = Self {
+            config,
+            device,
+            queue,
+            pipeline,
+            params,
+            cells,
+            counts,
+            bind_group,
+            cells_readback,
+            counts_readback,
+        };
+        sim.seed();
+
+        Some(sim)
+    }
+
+    /// The config this sim is currently running with
+    pub fn config(&self) -> &SimConfig {
+        &self.config
+    }
+
+    /// Start over with `config`, which `GpuSim::supports` must be true for
+    pub fn set_config(&mut self, config: SimConfig) {
+        assert!(Self::supports(&config), "GpuSim can't iterate {:?}", config);
+
+        self.config = config;
+        self.seed();
+    }
+
+    /// Reset the sim state to a fresh object
+    pub fn reset(&mut self) {
+        self.seed();
+    }
+
+    /// Seed every cell from the config on the CPU, and upload them
+    fn seed(&mut self) {
+        let config = self.config;
+        let len = config.fb_dims.x * config.fb_dims.y;
+        let cells: Vec<GpuCell> = (0..len)
+            .map(|idx| GpuCell::new(&config.new_cell::<f32>(idx)))
+            .collect();
+        let size = std::mem::size_of_val(cells.as_slice()) as u64;
+
+        if size == self.cells.size() {
+            self.queue
+                .write_buffer(&self.cells, 0, bytemuck::cast_slice(&cells));
+        } else {
+            self.cells = cells_buffer(&self.device, &cells);
+            self.cells_readback = readback_buffer(&self.device, size);
+            self.bind_group = bind_group(
+                &self.device,
+                &self.pipeline,
+                &self.params,
+                &self.cells,
+                &self.counts,
+            );
+        }
+    }
+
+    /// Step every cell once, like `Sim::update`
+    pub fn update(&mut self) -> UpdateStats {
+        self.update_n(1)
+    }
+
+    /// Step every cell `n` times in a single dispatch
+    ///
+    /// Returns how many cells are still active, and how many escaped during the `n` steps.
+    pub fn update_n(&mut self, n: u32) -> UpdateStats {
+        let len = self.config.fb_dims.x * self.config.fb_dims.y;
+        let params = Params {
+            len,
+            steps: n,
+            max_iters: self.config.max_iters,
+            step_radius_sqr: self.config.step_radius_sqr as f32,
+        };
+        self.queue
+            .write_buffer(&self.params, 0, bytemuck::bytes_of(&params));
+        self.queue
+            .write_buffer(&self.counts, 0, bytemuck::cast_slice(&[0u32; 2]));
+
+        // Too many workgroups for one axis wrap onto the next, and the shader unwraps them
+        let groups = len.div_ceil(WORKGROUP_SIZE);
+        let groups_x = groups.min(MAX_GROUPS_PER_AXIS);
+        let groups_y = groups.div_ceil(groups_x);
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &self.bind_group, &[]);
+            pass.dispatch_workgroups(groups_x, groups_y, 1);
+        }
+        encoder.copy_buffer_to_buffer(&self.counts, 0, &self.counts_readback, 0, 8);
+        self.queue.submit([encoder.finish()]);
+
+        let counts: Vec<u32> = self.read(&self.counts_readback);
+        UpdateStats {
+            active: counts[0],
+            escaped_this_step: counts[1],
+        }
+    }
+
+    /// How many iterations each cell has run, and whether it's escaped, in framebuffer order
+    pub fn escape_counts(&self) -> Vec<(u32, bool)> {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        encoder.copy_buffer_to_buffer(&self.cells, 0, &self.cells_readback, 0, self.cells.size());
+        self.queue.submit([encoder.finish()]);
+
+        self.read::<GpuCell>(&self.cells_readback)
+            .iter()
+            .map(|cell| (cell.iters, cell.has_escaped()))
+            .collect()
+    }
+
+    /// Draw the cells into `fb` with the plain palette, like `palette::with_plain_colors`
+    pub fn draw(&self, fb: &mut [u32]) {
+        let counts = self.escape_counts();
+        assert_eq!(fb.len(), counts.len());
+
+        for (pixel, &(iters, escaped)) in fb.iter_mut().zip(&counts) {
+            *pixel = if escaped {
+                encode(palette::plain_color(iters))
+            } else {
+                0
+            };
+        }
+    }
+
+    /// Wait for the GPU to finish, and copy out the contents of `buffer`, a readback buffer
+    fn read<T: Pod>(&self, buffer: &wgpu::Buffer) -> Vec<T> {
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| {
+            result.expect("Failed to read back from the GPU")
+        });
+        self.device
+            .poll(wgpu::PollType::Wait)
+            .expect("Failed to wait for the GPU");
+
+        let values = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+        buffer.unmap();
+        values
+    }
+}
+
+fn cells_buffer(device: &wgpu::Device, cells: &[GpuCell]) -> wgpu::Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("cells"),
+        contents: bytemuck::cast_slice(cells),
+        usage: wgpu::BufferUsages::STORAGE
+            | wgpu::BufferUsages::COPY_SRC
+            | wgpu::BufferUsages::COPY_DST,
+    })
+}
+
+fn readback_buffer(device: &wgpu::Device, size: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("readback"),
+        size,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn bind_group(
+    device: &wgpu::Device,
+    pipeline: &wgpu::ComputePipeline,
+    params: &wgpu::Buffer,
+    cells: &wgpu::Buffer,
+    counts: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: cells.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: counts.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Sim;
+    use wgpu::naga;
+
+    #[test]
+    fn shader_is_valid_wgsl() {
+        let module = naga::front::wgsl::parse_str(include_str!("gpu.wgsl")).unwrap();
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::default(),
+        )
+        .validate(&module)
+        .unwrap();
+    }
+
+    #[test]
+    fn escape_counts_match_an_f32_sim() {
+        let config = SimConfig::builder()
+            .fb_dims(64, 48)
+            .max_iters(200)
+            .build()
+            .expect("Expected a non-empty framebuffer");
+        let Some(mut gpu) = GpuSim::new(config) else {
+            eprintln!("No GPU to test GpuSim on");
+            return;
+        };
+
+        let mut sim = Sim::<f32>::with_precision(config);
+        sim.run_until_done(u32::MAX);
+        while gpu.update_n(64).active > 0 {}
+
+        // The GPU may fuse multiplies and adds, so a few cells right on the boundary can differ
+        let counts = gpu.escape_counts();
+        let differing = counts
+            .iter()
+            .zip(sim.cells())
+            .filter(|(&(iters, escaped), cell)| {
+                escaped != cell.has_escaped || (escaped && iters != cell.iters)
+            })
+            .count();
+        assert!(differing < counts.len() / 100, "{}", differing);
+
+        // Starting over gives the same render again
+        gpu.reset();
+        assert_eq!(gpu.update_n(u32::MAX).active, 0);
+        assert_eq!(gpu.escape_counts(), counts);
+    }
+}
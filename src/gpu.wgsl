@@ -0,0 +1,79 @@
+// Step cells of the quadratic Mandelbrot or Julia set, like `GridCell::step` in f32
+//
+// Each invocation runs one cell for up to `params.steps` iterations. See `gpu.rs` for the layout of
+// the buffers.
+
+// Values of `Cell::state`. `REACHED_BAILOUT` and up are done, as are cells seeded inside the set.
+const ITERATING: u32 = 0u;
+const ESCAPED: u32 = 1u;
+const REACHED_BAILOUT: u32 = 2u;
+const REACHED_MAX: u32 = 3u;
+
+const WORKGROUP_SIZE: u32 = 64u;
+
+struct Params {
+    len: u32,
+    steps: u32,
+    max_iters: u32,
+    step_radius_sqr: f32,
+}
+
+struct Cell {
+    z: vec2<f32>,
+    c: vec2<f32>,
+    iters: u32,
+    state: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> cells: array<Cell>;
+
+// Cells still iterating after this dispatch, and cells that escaped during it
+@group(0) @binding(2) var<storage, read_write> counts: array<atomic<u32>, 2>;
+
+@compute @workgroup_size(64)
+fn step(
+    @builtin(global_invocation_id) id: vec3<u32>,
+    @builtin(num_workgroups) groups: vec3<u32>,
+) {
+    let idx = id.x + id.y * groups.x * WORKGROUP_SIZE;
+    if idx >= params.len {
+        return;
+    }
+
+    var cell = cells[idx];
+    if cell.state >= REACHED_BAILOUT {
+        return;
+    }
+
+    let was_escaped = cell.state == ESCAPED;
+    for (var i = 0u; i < params.steps; i++) {
+        if cell.state == ITERATING && cell.iters >= params.max_iters {
+            cell.state = REACHED_MAX;
+            break;
+        }
+        cell.iters += 1u;
+
+        // The same operations as `z * z + c` on `Complex<f32>`
+        let z = cell.z;
+        cell.z = vec2<f32>(z.x * z.x - z.y * z.y, z.x * z.y + z.y * z.x) + cell.c;
+
+        let norm_sqr = cell.z.x * cell.z.x + cell.z.y * cell.z.y;
+        if cell.state == ITERATING && norm_sqr > 4.0 {
+            cell.state = ESCAPED;
+        }
+        if cell.state == ESCAPED && norm_sqr > params.step_radius_sqr {
+            cell.state = REACHED_BAILOUT;
+            break;
+        }
+    }
+
+    cells[idx] = cell;
+    if cell.state < REACHED_BAILOUT {
+        atomicAdd(&counts[0], 1u);
+    }
+    let escaped = cell.state == ESCAPED || cell.state == REACHED_BAILOUT;
+    if escaped && !was_escaped {
+        atomicAdd(&counts[1], 1u);
+    }
+}
@@ -1,12 +1,75 @@
-use num::Complex;
+use num::{Complex, Float};
 use ultraviolet::{DVec2, DVec3, UVec2};
 
 #[cfg(feature = "rayon")]
 use rayon::prelude::*;
 
+use math::{ToComplex, ToDVec2};
+use palette::Palette;
+
+mod accumulate;
+pub mod buddhabrot;
+mod builder;
+mod export;
+#[cfg(feature = "exr")]
+mod exr;
+#[cfg(feature = "wgpu")]
+mod gpu;
+mod mariani_silver;
+pub mod math;
+mod noise;
 pub mod palette;
+mod perturb;
+mod post;
+mod precision;
+#[cfg(feature = "simd")]
+mod simd;
+mod terminal;
+pub mod text;
+mod tile;
+
+pub use accumulate::{render_zoom_blurred, Accumulator};
+pub use builder::{BuildError, SimConfigBuilder};
+#[cfg(feature = "image")]
+#[allow(deprecated)]
+pub use export::load_config_from_png;
+#[cfg(feature = "serde")]
+pub use export::{load_bookmarks, save_bookmarks};
+#[cfg(feature = "image")]
+pub use export::{read_png_params, save_framebuffer_png, save_png, save_png16, save_png_tiled};
+#[cfg(feature = "wgpu")]
+pub use gpu::GpuSim;
+pub use noise::NoiseKind;
+pub use post::{bicubic_upscale_f32, bloom, downsample};
+pub use precision::precision_divergence;
+pub use tile::hilbert_tile_order;
+
+/// The default for `SimConfig::step_radius_sqr`
+pub const DEFAULT_STEP_RADIUS_SQR: f64 = 1_000. * 1_000.;
+
+/// A sensible `SimConfig::max_iters` for frames near the default zoom
+pub const DEFAULT_MAX_ITERS: u32 = 1_000;
+
+/// How many iterations a cell goes between saving `z_ref` to check for cycles against
+const PERIOD_CHECK_INTERVAL: u32 = 20;
+
+/// How close, squared, `z` needs to come back to `z_ref` to count as a cycle
+const PERIOD_EPSILON: f64 = 1e-20;
+
+/// Maximum number of iterations to follow a single orbit for
+const ORBIT_ITERS: u32 = 1_000;
+
+/// Number of samples along the longer axis of the coarse grid used by `Sim::reframe_to_fit`
+const REFRAME_SAMPLES: u32 = 128;
 
-const R2: u32 = 1_000 * 1_000;
+/// Number of iterations to run the coarse grid used by `Sim::reframe_to_fit`
+const REFRAME_ITERS: u32 = 64;
+
+/// Iterations per pixel that `Sim::reframe_to_fit` considers part of the boundary
+const REFRAME_GRADIENT: f32 = 4.;
+
+/// Fraction of the boundary's size to add on each side by `Sim::reframe_to_fit`
+const REFRAME_MARGIN: f64 = 0.1;
 
 /// Construct a color for use with minifb
 ///
@@ -17,7 +80,122 @@ const fn rgb(r: u8, g: u8, b: u8) -> u32 {
     (r << 16) | (g << 8) | b
 }
 
+/// Pixel drawn for colors with a `NaN` or infinite component
+///
+/// Bright magenta is hard to miss, unlike the black that a plain `as u8` cast would give.
+const NON_FINITE_COLOR: u32 = rgb(255, 0, 255);
+
+/// Encode a color from a palette as a pixel for use with minifb
+///
+/// Colors that aren't finite are drawn as `NON_FINITE_COLOR`, so numerical problems in a palette
+/// or fractal show up instead of hiding in the black of the set.
+#[inline]
+fn encode(c: DVec3) -> u32 {
+    encode_dithered(c, 0.)
+}
+
+/// Like `encode`, but as RGBA with 16 bits per channel
+#[inline]
+fn encode16(c: DVec3) -> [u16; 4] {
+    if !(c.x.is_finite() && c.y.is_finite() && c.z.is_finite()) {
+        let [_, r, g, b] = NON_FINITE_COLOR.to_be_bytes();
+        return [r, g, b, 0xff].map(|channel| 257 * channel as u16);
+    }
+
+    let c = c.clamped(DVec3::new(0., 0., 0.), DVec3::new(1., 1., 1.));
+    let c = 65535. * c;
+
+    [c.x as u16, c.y as u16, c.z as u16, u16::MAX]
+}
+
+/// Like `encode`, but add `threshold` (in `[0, 1)`, in units of the 8-bit step) before truncating
+#[inline]
+fn encode_dithered(c: DVec3, threshold: f64) -> u32 {
+    if !(c.x.is_finite() && c.y.is_finite() && c.z.is_finite()) {
+        return NON_FINITE_COLOR;
+    }
+
+    // Clamp and scale all output from `color` into the range for our 8-bit channels: [0, 255]
+    let c = c.clamped(DVec3::new(0., 0., 0.), DVec3::new(1., 1., 1.));
+    let c = 255. * c + DVec3::broadcast(threshold);
+
+    rgb(c.x as u8, c.y as u8, c.z as u8)
+}
+
+/// How pixels of the frame map onto the complex plane
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CoordTransform {
+    /// Pixels map linearly onto the rectangle between `frame_min` and `frame_max`
+    #[default]
+    Identity,
+
+    /// The whole complex plane is wrapped onto a sphere, and the sphere is drawn as a disk
+    ///
+    /// The disk is inscribed in the frame, centered on the frame's center `p`, with radius `R` of
+    /// half of the frame's shorter side. A pixel at normalized distance `rho` from the center
+    /// (`rho = 1` on the edge of the disk) sits at polar angle `theta = pi * rho` from the south pole
+    /// of the sphere. Stereographic projection from the north pole then puts it at distance
+    /// `R * tan(theta / 2)` from `p`, in the same direction as the pixel.
+    ///
+    /// So the sphere's equator (`rho = 1/2`) is the circle of radius `R` that `Identity` would
+    /// inscribe in the frame, the rest of the plane is compressed into the outer half of the disk,
+    /// and the point at infinity is the edge of the disk. Pixels outside the disk also map to (nearly)
+    /// infinity.
+    Stereographic,
+}
+
+/// Which fractal to iterate
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FractalKind {
+    /// Each pixel is `c`, and iteration starts from `z = 0`
+    #[default]
+    Mandelbrot,
+
+    /// Each pixel is the starting `z`, and every pixel shares the same `c`
+    Julia { c: Complex<f64> },
+}
+
+/// The formula that each step applies to `z`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Formula {
+    /// `z = z^power + c`
+    #[default]
+    Standard,
+
+    /// `z = (|Re z| + i|Im z|)^power + c`, which makes the Burning Ship from the Mandelbrot set
+    ///
+    /// Taking absolute values isn't complex differentiable, so `dz` is only approximate: it's
+    /// reflected along with `z`, which is enough to keep the lighting palettes usable.
+    BurningShip,
+}
+
+/// Options for how `Sim::draw_with_options` turns colors into pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawOptions {
+    /// Gamma to encode colors with: each channel is raised to `1 / gamma` before it's quantized
+    ///
+    /// `1.0` keeps the raw, linear output of the palette, and `2.2` approximates sRGB, which
+    /// brightens the dark, muddy parts of palettes like the smooth stripes and lambert shading.
+    pub gamma: f64,
+
+    /// Noise to break up banding with
+    pub noise: NoiseKind,
+}
+
+impl Default for DrawOptions {
+    fn default() -> Self {
+        Self {
+            gamma: 1.,
+            noise: NoiseKind::None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SimConfig {
     /// 2D Dimensions of the framebuffer
     pub fb_dims: UVec2,
@@ -27,58 +205,367 @@ pub struct SimConfig {
 
     /// Complex point of the upper-right (+x & +y) point of the frame
     pub frame_max: DVec2,
+
+    /// How pixels map into the frame
+    pub transform: CoordTransform,
+
+    /// Which fractal to iterate
+    pub kind: FractalKind,
+
+    /// Cells that haven't escaped after this many iterations are treated as inside the set, and
+    /// aren't stepped any further
+    pub max_iters: u32,
+
+    /// Power of `z` in the iteration, `z = z^power + c`
+    ///
+    /// `2` gives the usual Mandelbrot and Julia sets, and higher powers give Multibrot sets.
+    pub power: u32,
+
+    /// The formula each step applies to `z`, before raising it to `power`
+    pub formula: Formula,
+
+    /// Supersampling factor: each pixel is covered by a `samples_per_axis` by `samples_per_axis`
+    /// block of cells, whose colors are averaged when drawing
+    ///
+    /// This must be at least `1`, which gives one cell per pixel. Memory and time both grow with the square of this, so `2` is
+    /// four times the cost of `1`, and `4` is sixteen times. See `grid_dims`.
+    #[cfg_attr(feature = "serde", serde(default = "default_samples_per_axis"))]
+    pub samples_per_axis: u32,
+
+    /// Escaped cells keep stepping until `|z|^2` passes this, and then they're done
+    ///
+    /// Escaping only takes `|z| > 2`, but smooth coloring and distance estimates get more accurate
+    /// the further `z` goes past that, so this is usually much larger. It's also the largest
+    /// coloring bailout a sim accepts, see `Sim::set_coloring_bailout`. Defaults to
+    /// `DEFAULT_STEP_RADIUS_SQR`.
+    #[cfg_attr(feature = "serde", serde(default = "default_step_radius_sqr"))]
+    pub step_radius_sqr: f64,
+}
+
+#[cfg(feature = "serde")]
+fn default_samples_per_axis() -> u32 {
+    1
+}
+
+#[cfg(feature = "serde")]
+fn default_step_radius_sqr() -> f64 {
+    DEFAULT_STEP_RADIUS_SQR
 }
 
 impl SimConfig {
+    /// Find the point of the complex plane that the pixel at framebuffer index `idx` samples
+    ///
+    /// Pixel `(x, y)` is normalized to `(x / width, y / height)` and linearly mapped onto the frame,
+    /// so the top-left corner of the framebuffer samples `frame_min.x`. The y axis is flipped: row 0
+    /// is the top of the image, and samples `frame_max.y`, since bigger y goes at the top. The
+    /// mapped point then goes through `transform`.
     #[inline]
-    fn idx_to_complex(&self, idx: u32) -> Complex<f64> {
+    pub fn idx_to_complex(&self, idx: u32) -> Complex<f64> {
         // Unpack out integer coordinates
         let x = idx % self.fb_dims.x;
         let y = idx / self.fb_dims.x;
 
+        self.pixel_to_complex(x as f64, y as f64)
+    }
+
+    /// Dimensions of the grid of cells, which is `fb_dims` scaled up by `samples_per_axis`
+    pub fn grid_dims(&self) -> UVec2 {
+        self.fb_dims * self.samples_per_axis
+    }
+
+    /// Find the point of the complex plane that the cell at grid index `idx` samples
+    ///
+    /// This is `idx_to_complex` for the cells of a supersampled grid: the cells of a pixel are spread
+    /// evenly across it, with the first at the same point that `idx_to_complex` gives the pixel.
+    #[inline]
+    pub(crate) fn grid_idx_to_complex(&self, idx: u32) -> Complex<f64> {
+        let samples = self.samples_per_axis;
+        let width = self.fb_dims.x * samples;
+
+        let x = (idx % width) as f64 / samples as f64;
+        let y = (idx / width) as f64 / samples as f64;
+
+        self.pixel_to_complex(x, y)
+    }
+
+    /// Map a (possibly fractional) pixel position to the complex plane, like `idx_to_complex`
+    #[inline]
+    fn pixel_to_complex(&self, x: f64, y: f64) -> Complex<f64> {
         // Normalize coordinates
-        let x: f64 = (x as f64) / (self.fb_dims.x as f64);
-        let y: f64 = (y as f64) / (self.fb_dims.y as f64);
+        let x: f64 = x / (self.fb_dims.x as f64);
+        let y: f64 = y / (self.fb_dims.y as f64);
 
         // Flip the buffer to put "bigger" y at the "top"
         let y: f64 = 1.0 - y;
 
         // Scale into the bounds space
-        let x = x * self.frame_max.x + (1.0 - x) * self.frame_min.x;
-        let y = y * self.frame_max.y + (1.0 - y) * self.frame_min.y;
+        let t = DVec2::new(x, y);
+        let p = t * self.frame_max + (DVec2::one() - t) * self.frame_min;
+
+        match self.transform {
+            CoordTransform::Identity => p.to_complex(),
+            CoordTransform::Stereographic => {
+                let (center, radius) = self.inscribed_circle();
 
-        Complex::new(x, y)
+                // Find the polar angle on the sphere from our distance to the center of the disk
+                let d = p - center;
+                let rho = (d.mag() / radius).min(1.);
+                let theta = std::f64::consts::PI * rho;
+
+                // And project it back onto the plane
+                if rho == 0. {
+                    center.to_complex()
+                } else {
+                    (center + d.normalized() * radius * (theta / 2.).tan()).to_complex()
+                }
+            }
+        }
+    }
+
+    /// Make a fresh cell for the cell at grid index `idx`
+    #[inline]
+    fn new_cell<F: SimFloat>(&self, idx: u32) -> GridCell<F> {
+        self.new_cell_at(self.grid_idx_to_complex(idx))
+    }
+
+    /// Make a fresh cell for the point `p` of the complex plane
+    ///
+    /// `p` is only as precise as an `f64`, whatever `F` the cell iterates in.
+    #[inline]
+    fn new_cell_at<F: SimFloat>(&self, p: Complex<f64>) -> GridCell<F> {
+        match self.kind {
+            FractalKind::Mandelbrot => {
+                let mut cell = GridCell::new(to_float(p));
+
+                // The biggest parts of the classic set are known ahead of time, so skip iterating them
+                if self.power == 2 && self.formula == Formula::Standard && in_main_bulbs(p) {
+                    cell.is_interior = true;
+                }
+
+                cell
+            }
+            FractalKind::Julia { c } => GridCell::new_julia(to_float(p), to_float(c)),
+        }
+    }
+
+    /// Make a fresh cell to replace `cell`, which is at `idx` of the grid
+    ///
+    /// This is `new_cell`, but Mandelbrot cells reuse their `c` instead of mapping `idx` back through
+    /// the frame.
+    #[inline]
+    fn fresh_cell<F: SimFloat>(&self, cell: &GridCell<F>, idx: u32) -> GridCell<F> {
+        match self.kind {
+            FractalKind::Mandelbrot => {
+                let mut fresh = GridCell::new(cell.c);
+
+                let c = Complex::new(
+                    cell.c.re.to_f64().unwrap_or(f64::NAN),
+                    cell.c.im.to_f64().unwrap_or(f64::NAN),
+                );
+                if self.power == 2 && self.formula == Formula::Standard && in_main_bulbs(c) {
+                    fresh.is_interior = true;
+                }
+
+                fresh
+            }
+            FractalKind::Julia { .. } => self.new_cell(idx),
+        }
+    }
+
+    /// Center and radius of the largest circle that fits in the frame
+    fn inscribed_circle(&self) -> (DVec2, f64) {
+        let center = 0.5 * (self.frame_min + self.frame_max);
+        let half = 0.5 * (self.frame_max - self.frame_min);
+
+        (center, half.x.min(half.y))
+    }
+
+    /// Whether the frame is symmetric about the real axis, and so is the fractal in it
+    ///
+    /// The Mandelbrot set of `z^n + c` is its own mirror image across the real axis, so a frame with
+    /// `frame_min.y == -frame_max.y` shows the same thing above and below the middle. The Burning
+    /// Ship's fold breaks the symmetry, as do Julia sets for most `c`.
+    fn is_mirrored(&self) -> bool {
+        matches!(self.kind, FractalKind::Mandelbrot)
+            && self.formula == Formula::Standard
+            && self.transform == CoordTransform::Identity
+            && self.frame_min.y == -self.frame_max.y
+    }
+
+    /// Find the framebuffer index of the pixel whose sample point is nearest to `c`
+    ///
+    /// This is the inverse of `idx_to_complex`, which makes it handy for mouse picking. Returns `None`
+    /// when `c` lies outside the frame. With `CoordTransform::Stereographic`, every pixel outside of
+    /// the inscribed circle samples the point at infinity, so only pixels inside of it round-trip.
+    pub fn complex_to_idx(&self, c: Complex<f64>) -> Option<u32> {
+        let p = self.complex_to_pixel(c)?;
+        Some(p.y * self.fb_dims.x + p.x)
+    }
+
+    /// Find the pixel whose sample point is nearest to `c`
+    ///
+    /// This is the inverse of `idx_to_complex`, and returns `None` when `c` lies outside the frame.
+    /// Every point from `frame_min` to `frame_max` inclusive lands on a pixel, even on the edges that
+    /// no pixel's sample point reaches.
+    #[inline]
+    fn complex_to_pixel(&self, c: Complex<f64>) -> Option<UVec2> {
+        // Undo the transform to find where `c` lands in the frame
+        let p = match self.transform {
+            CoordTransform::Identity => c.to_dvec2(),
+            CoordTransform::Stereographic => {
+                let (center, radius) = self.inscribed_circle();
+
+                let d = c.to_dvec2() - center;
+                if d.mag() == 0. {
+                    center
+                } else {
+                    let theta = 2. * (d.mag() / radius).atan();
+                    let rho = theta / std::f64::consts::PI;
+
+                    center + d.normalized() * radius * rho
+                }
+            }
+        };
+
+        // Normalize coordinates
+        let t = (p - self.frame_min) / (self.frame_max - self.frame_min);
+
+        if !((0. ..=1.).contains(&t.x) && (0. ..=1.).contains(&t.y)) {
+            return None;
+        }
+
+        // Flip the buffer to put "bigger" y at the "top"
+        let (x, y) = (t.x, 1.0 - t.y);
+
+        // Scale into integer coordinates. The right and bottom edges of the frame round to one past
+        // the last pixel, so clamp them back onto it.
+        let x = (x * self.fb_dims.x as f64).round() as u32;
+        let y = (y * self.fb_dims.y as f64).round() as u32;
+
+        Some(UVec2::new(
+            x.min(self.fb_dims.x - 1),
+            y.min(self.fb_dims.y - 1),
+        ))
     }
 }
 
-#[derive(Copy, Clone, Debug)]
-pub struct GridCell {
-    pub c: Complex<f64>,
-    pub z: Complex<f64>,
-    pub dc: Complex<f64>,
-    pub dz: Complex<f64>,
+/// Floating point types that cells can iterate in
+///
+/// Anything that implements `num::Float` and can be shared between threads qualifies, so `f32` and
+/// `f64` both work, as would a software float with more precision.
+pub trait SimFloat: Float + Send + Sync + std::fmt::Debug + 'static {}
+
+impl<F> SimFloat for F where F: Float + Send + Sync + std::fmt::Debug + 'static {}
+
+/// Convert an `f64` constant to `F`
+#[inline]
+fn float<F: SimFloat>(x: f64) -> F {
+    F::from(x).expect("Expected every SimFloat to represent an f64, if approximately")
+}
+
+/// Convert an `f64` complex number to `F`
+#[inline]
+fn to_float<F: SimFloat>(z: Complex<f64>) -> Complex<F> {
+    Complex::new(float(z.re), float(z.im))
+}
+
+/// One point of the grid, iterating in `F`
+///
+/// `F` defaults to `f64`, which the palettes and drawing functions all use. Other precisions can
+/// run with `Sim<F>`, and read back through `Sim::cells`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GridCell<F = f64> {
+    pub c: Complex<F>,
+    pub z: Complex<F>,
+    pub dc: Complex<F>,
+    pub dz: Complex<F>,
 
     pub iters: u32,
     pub has_escaped: bool,
+
+    /// Whether this cell gave up after `SimConfig::max_iters` iterations without escaping
+    pub reached_max: bool,
+
+    /// Continuous iteration count, `iters + 1 - log_power(log2|z|)`, once the cell has escaped
+    ///
+    /// Unlike `iters`, this varies smoothly across the exterior, so palettes that use it don't band.
+    /// It's updated on every step after escaping, and gets more accurate the further `z` goes past the
+    /// escape radius. It's `0` until the cell escapes.
+    pub smooth_iters: f64,
+
+    /// A recent `z`, saved every `PERIOD_CHECK_INTERVAL` iterations, to detect cycles against
+    pub z_ref: Complex<F>,
+
+    /// Whether `z` returned to `z_ref`, so the orbit is periodic and the cell is inside the set
+    pub is_interior: bool,
+
+    /// Whether this cell escaped and was stepped past `SimConfig::step_radius_sqr`
+    pub reached_bailout: bool,
 }
 
-impl GridCell {
-    pub fn new(c: Complex<f64>) -> Self {
+impl<F: SimFloat> GridCell<F> {
+    pub fn new(c: Complex<F>) -> Self {
+        let z = Complex::new(F::zero(), F::zero());
+
         GridCell {
             c,
-            z: Complex::new(0., 0.),
-            dc: Complex::new(1., 0.),
-            dz: Complex::new(1., 0.),
+            z,
+            dc: Complex::new(F::one(), F::zero()),
+            dz: Complex::new(F::one(), F::zero()),
 
             iters: 0,
             has_escaped: false,
+            reached_max: false,
+            smooth_iters: 0.,
+            z_ref: z,
+            is_interior: false,
+            reached_bailout: false,
         }
     }
 
-    pub fn step(&mut self) {
-        // Use a separate threshold for when to stop stepping.
-        // This is generally much larger than |2|, but produces better coloring schemes.
-        if self.z.norm_sqr() > R2 as f64 {
+    /// Make a cell for a Julia set, starting from `z` with the shared constant `c`
+    ///
+    /// `c` doesn't vary between pixels, so only the derivative with respect to the starting `z` is
+    /// tracked.
+    pub fn new_julia(z: Complex<F>, c: Complex<F>) -> Self {
+        GridCell {
+            c,
+            z,
+            dc: Complex::new(F::zero(), F::zero()),
+            dz: Complex::new(F::one(), F::zero()),
+
+            iters: 0,
+            has_escaped: false,
+            reached_max: false,
+            smooth_iters: 0.,
+            z_ref: z,
+            is_interior: false,
+            reached_bailout: false,
+        }
+    }
+
+    /// Whether stepping this cell any further would change it
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        self.reached_max || self.is_interior || self.reached_bailout
+    }
+
+    /// Advance this cell by one iteration of `config`'s formula
+    ///
+    /// Once the cell has run `config.max_iters` iterations without escaping, it's flagged as
+    /// `reached_max` and stops advancing. Cells that have escaped keep going until they're done,
+    /// regardless of `max_iters`. Cells whose orbit comes back around to `z_ref` are periodic, so
+    /// they're flagged as `is_interior` and stop advancing too.
+    ///
+    /// Stepping past the escape is what smooth coloring relies on, so it's part of the contract: an
+    /// escaped cell's `iters` and `z` are those of the first iteration where `|z|^2` passes
+    /// `config.step_radius_sqr`, not of the iteration where it first passed `|z| > 2`.
+    pub fn step(&mut self, config: &SimConfig) {
+        if self.is_done() {
+            return;
+        }
+        if self.iters >= config.max_iters && !self.has_escaped {
+            self.reached_max = true;
             return;
         }
 
@@ -88,107 +575,2333 @@ impl GridCell {
         // Copy values out so we can update them
         let GridCell { c, z, dc, dz, .. } = *self;
 
-        self.z = z * z + c;
-        self.dz = dz * 2. * z + dc;
+        let (z, dz) = match config.formula {
+            Formula::Standard => (z, dz),
+            Formula::BurningShip => {
+                // Fold z into the first quadrant, and reflect dz the same way
+                let sign = |x: F| if x < F::zero() { -F::one() } else { F::one() };
+                let (sx, sy) = (sign(z.re), sign(z.im));
+                (
+                    Complex::new(sx * z.re, sy * z.im),
+                    Complex::new(sx * dz.re, sy * dz.im),
+                )
+            }
+        };
+
+        if config.power == 2 {
+            self.z = z * z + c;
+            self.dz = dz * float::<F>(2.) * z + dc;
+        } else {
+            // z^(n-1) is needed for the derivative anyway, so build z^n from it
+            let z_n1 = z.powu(config.power.saturating_sub(1));
+            self.z = z_n1 * z + c;
+            self.dz = dz * float::<F>(config.power as f64) * z_n1 + dc;
+        }
 
         // Check our typical escape condition
-        if self.z.norm_sqr() > 4.0 {
+        if self.z.norm_sqr() > float(4.) {
             self.has_escaped = true;
         }
+
+        // An orbit that comes back to where it was is periodic, and will never escape
+        if !self.has_escaped {
+            if (self.z - self.z_ref).norm_sqr() < float(PERIOD_EPSILON) {
+                self.is_interior = true;
+            } else if self.iters.is_multiple_of(PERIOD_CHECK_INTERVAL) {
+                self.z_ref = self.z;
+            }
+        }
+
+        // Use a separate threshold for when to stop stepping.
+        // This is generally much larger than |2|, but produces better coloring schemes.
+        if self.has_escaped && self.z.norm_sqr() > float(config.step_radius_sqr) {
+            self.reached_bailout = true;
+        }
+
+        if self.has_escaped {
+            let power = config.power.max(2) as f64;
+            let log_z = self.z.norm().log2().ln().to_f64().unwrap_or(f64::NAN);
+            self.smooth_iters = self.iters as f64 + 1. - log_z / power.ln();
+        }
+    }
+
+    /// This cell, reflected across the real axis
+    ///
+    /// A Mandelbrot cell for `conj(c)` goes through exactly the conjugates of this cell's values, so
+    /// this is what it would be after the same number of steps.
+    #[inline]
+    fn conj(&self) -> Self {
+        GridCell {
+            c: self.c.conj(),
+            z: self.z.conj(),
+            dc: self.dc.conj(),
+            dz: self.dz.conj(),
+            z_ref: self.z_ref.conj(),
+            ..*self
+        }
+    }
+
+    /// Estimate the distance from `c` to the boundary of the set, in complex-plane units
+    ///
+    /// This is the exterior distance estimate `|z| * ln|z| / |dz|`, so it is only available once the
+    /// cell has escaped. It becomes more accurate the further `z` has been stepped past the escape
+    /// radius.
+    pub fn distance_estimate(&self) -> Option<F> {
+        if !self.has_escaped {
+            return None;
+        }
+
+        let z = self.z.norm();
+        let dz = self.dz.norm();
+
+        // A vanishing derivative means we can't say anything useful about the distance
+        if dz == F::zero() || !dz.is_finite() {
+            return None;
+        }
+
+        Some(z * z.ln() / dz)
+    }
+}
+
+/// What happened to the grid during one `Sim::update`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct UpdateStats {
+    /// Cells that would still change if the sim were updated again, like `Sim::active_count`
+    pub active: u32,
+
+    /// Cells that escaped on this update
+    pub escaped_this_step: u32,
+}
+
+impl UpdateStats {
+    /// Step `cell` with `config`, and count what happened to it
+    #[inline]
+    fn step<F: SimFloat>(cell: &mut GridCell<F>, config: &SimConfig) -> Self {
+        let had_escaped = cell.has_escaped;
+        cell.step(config);
+
+        Self::of_cell(cell, had_escaped)
+    }
+
+    /// Like `step`, but also flag the cell as `dirty` if the step changes it
+    #[inline]
+    fn step_marking<F: SimFloat>(
+        cell: &mut GridCell<F>,
+        dirty: &mut bool,
+        config: &SimConfig,
+    ) -> Self {
+        *dirty |= !cell.is_done();
+        Self::step(cell, config)
+    }
+
+    /// Stats for a single cell that was just stepped, and whether it had escaped before
+    #[inline]
+    fn of_cell<F: SimFloat>(cell: &GridCell<F>, had_escaped: bool) -> Self {
+        Self {
+            active: !cell.is_done() as u32,
+            escaped_this_step: (cell.has_escaped && !had_escaped) as u32,
+        }
+    }
+
+    /// Combine the stats of two parts of the grid
+    #[inline]
+    fn merge(self, other: Self) -> Self {
+        Self {
+            active: self.active + other.active,
+            escaped_this_step: self.escaped_this_step + other.escaped_this_step,
+        }
     }
 }
 
-pub struct Sim {
+/// A grid of cells for the frame of a `SimConfig`, iterating in `F`
+///
+/// `Sim::new` iterates in `f64`, and `with_precision` picks any other `SimFloat`. Updating works in
+/// any of them, but drawing, palettes, and the other analysis all expect the default of `f64`.
+/// The frame and each cell's starting point are still `f64`s, so precisions beyond
+/// `f64` make the iteration itself more accurate, but can't place pixels any closer together.
+pub struct Sim<F = f64> {
     config: SimConfig,
-    grid: Vec<GridCell>,
+    grid: Vec<GridCell<F>>,
+
+    /// Squared radius that palettes should treat as escaped when coloring
+    coloring_r2: f64,
+
+    /// What to add to escaped cells' `smooth_iters` to count from `coloring_r2` instead of
+    /// `step_radius_sqr`, see `set_coloring_bailout`
+    smooth_offset: f64,
+
+    /// Framebuffer that `render_rgba` draws into, kept so repeated renders don't reallocate it
+    scratch_fb: Vec<u32>,
+
+    /// Reference orbit and per-cell offsets for `update_perturbed`, once it's been called
+    perturbation: Option<perturb::Perturbation>,
+
+    /// Which cells may have changed since `draw_dirty` last drew them, one per cell of `grid`
+    dirty: Vec<bool>,
+
+    /// Whether the cells are the pixel grid of `config`, rather than points given to `with_seeds`
+    ///
+    /// Paths that work out a cell's point from its index, like mirroring and perturbation, need it.
+    pixel_grid: bool,
+
+    /// Whether each cell of the top half is still the reflection of its partner in the bottom half
+    ///
+    /// Seeding and `update` keep them in step, but paths that step only some cells, or step them
+    /// differently, don't, and then `update` can't mirror until the next reset.
+    mirror_in_step: bool,
+
+    /// Threads that `update` and `draw` run on, instead of rayon's global pool, if set
+    #[cfg(feature = "rayon")]
+    pool: Option<rayon::ThreadPool>,
 }
 
 impl Sim {
     pub fn new(config: SimConfig) -> Self {
-        let framebuffer_size = config.fb_dims.x * config.fb_dims.y;
-        let mut grid = Vec::with_capacity(framebuffer_size as usize);
+        Self::with_precision(config)
+    }
+
+    /// Make a sim whose `update` and `draw` run on their own pool of `threads` threads
+    ///
+    /// By default, rayon spreads work over every core, which can starve everything else on a shared
+    /// machine. This caps it. `threads == 0` means rayon's default, which is all of them.
+    ///
+    /// Only `update` (and so `update_n` and `run_until_done`), `draw`, and `draw_dirty` run on the
+    /// pool. Everything else still uses rayon's global pool.
+    #[cfg(feature = "rayon")]
+    pub fn new_with_threads(config: SimConfig, threads: usize) -> Self {
+        let mut sim = Self::new(config);
+        if threads > 0 {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .expect("Failed to start a thread pool");
+            sim.pool = Some(pool);
+        }
+
+        sim
+    }
+
+    /// Make a sim with one cell per point in `seeds`, instead of one per pixel
+    ///
+    /// This lets the `update` and analysis machinery run on any set of points, like a jittered or
+    /// importance-sampled set. `config` still picks the fractal kind, and its frame is kept for
+    /// mapping coordinates, but it doesn't decide where cells are.
+    ///
+    /// `draw` and friends assume the cells are laid out like the pixel grid, so this is for
+    /// analysis, not for drawing to a framebuffer. Likewise `resize`, `set_frame`, and `set_kind`
+    /// go back to the pixel grid. `reset` keeps the seeds of Mandelbrot sims, since it reuses each
+    /// cell's `c`, but Julia sims go back to the pixel grid.
+    pub fn with_seeds(config: SimConfig, seeds: Vec<Complex<f64>>) -> Self {
+        let grid: Vec<GridCell> = seeds.into_iter().map(|p| config.new_cell_at(p)).collect();
 
-        for idx in 0..framebuffer_size {
-            let c = config.idx_to_complex(idx);
-            grid.push(GridCell::new(c));
+        Self {
+            config,
+            dirty: vec![true; grid.len()],
+            grid,
+            coloring_r2: config.step_radius_sqr,
+            smooth_offset: 0.,
+            scratch_fb: vec![],
+            perturbation: None,
+            pixel_grid: false,
+            mirror_in_step: true,
+            #[cfg(feature = "rayon")]
+            pool: None,
         }
+    }
+}
+
+impl<F: SimFloat> Sim<F> {
+    /// Make a sim whose cells iterate in `F`, like `Sim::<f32>::with_precision(config)`
+    ///
+    /// `Sim::new` is this, for the default of `f64`.
+    pub fn with_precision(config: SimConfig) -> Self {
+        assert!(
+            config.samples_per_axis >= 1,
+            "Expected at least one sample per axis"
+        );
+
+        let grid_dims = config.grid_dims();
+        let grid_size = grid_dims.x * grid_dims.y;
+
+        // Every cell is independent, and collecting an indexed iterator keeps them in order
+        #[cfg(feature = "rayon")]
+        let grid: Vec<GridCell<F>> = (0..grid_size)
+            .into_par_iter()
+            .map(|idx| config.new_cell(idx))
+            .collect();
+
+        #[cfg(not(feature = "rayon"))]
+        let grid: Vec<GridCell<F>> = (0..grid_size).map(|idx| config.new_cell(idx)).collect();
 
-        assert_eq!(grid.len(), framebuffer_size as usize);
+        assert_eq!(grid.len(), grid_size as usize);
 
-        Self { config, grid }
+        Self {
+            config,
+            dirty: vec![true; grid.len()],
+            grid,
+            coloring_r2: config.step_radius_sqr,
+            smooth_offset: 0.,
+            scratch_fb: vec![],
+            perturbation: None,
+            pixel_grid: true,
+            mirror_in_step: true,
+            #[cfg(feature = "rayon")]
+            pool: None,
+        }
     }
 
     /// Reset the sim state to a fresh object
+    ///
+    /// The grid is reset in place. Mandelbrot cells keep the `c` they were seeded with, so nothing
+    /// is mapped back through the frame, while Julia cells are re-seeded from their index, since
+    /// they don't remember where they started.
     pub fn reset(&mut self) {
-        self.grid.clear();
+        // Julia cells need their index to be laid out like the pixel grid
+        let is_julia = matches!(self.config.kind, FractalKind::Julia { .. });
+        if is_julia && !self.pixel_grid {
+            self.reseed();
+            return;
+        }
+
+        self.perturbation = None;
+        let config = self.config;
+
+        #[cfg(feature = "rayon")]
+        {
+            self.grid
+                .par_iter_mut()
+                .enumerate()
+                .for_each(|(idx, cell)| *cell = config.fresh_cell(cell, idx as u32));
+        }
 
-        let framebuffer_size = self.config.fb_dims.x * self.config.fb_dims.y;
-        for idx in 0..framebuffer_size {
-            let c: Complex<_> = self.config.idx_to_complex(idx);
-            self.grid.push(GridCell::new(c));
+        #[cfg(not(feature = "rayon"))]
+        {
+            self.grid
+                .iter_mut()
+                .enumerate()
+                .for_each(|(idx, cell)| *cell = config.fresh_cell(cell, idx as u32));
         }
+
+        self.mirror_in_step = true;
+        self.mark_all_dirty();
     }
 
-    pub fn update(&mut self) {
+    /// Re-seed every cell of the grid from the config, after something that moves the cells
+    fn reseed(&mut self) {
+        self.grid.clear();
+        self.perturbation = None;
+        self.pixel_grid = true;
+        self.mirror_in_step = true;
+
+        let config = self.config;
+        let grid_dims = config.grid_dims();
+
         #[cfg(feature = "rayon")]
         {
-            self.grid.par_iter_mut().for_each(|cell| {
-                cell.step();
-            })
+            self.grid.par_extend(
+                (0..grid_dims.x * grid_dims.y)
+                    .into_par_iter()
+                    .map(|idx| config.new_cell(idx)),
+            );
         }
 
         #[cfg(not(feature = "rayon"))]
         {
-            for cell in self.grid.iter_mut() {
-                cell.step();
-            }
+            self.grid
+                .extend((0..grid_dims.x * grid_dims.y).map(|idx| config.new_cell(idx)));
         }
+
+        self.mark_all_dirty();
     }
 
-    pub fn draw<ColorFn>(&mut self, fb: &mut [u32], color: ColorFn)
-    where
-        ColorFn: Fn(&GridCell) -> DVec3 + Sync,
-    {
-        assert_eq!(fb.len(), self.grid.len());
+    /// Flag every cell as changed, so the next `draw_dirty` redraws the whole framebuffer
+    ///
+    /// Call this after anything that changes colors without changing cells, like switching
+    /// palettes or drawing over the framebuffer.
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty.clear();
+        self.dirty.resize(self.grid.len(), true);
+    }
+
+    /// The config this sim is currently running with
+    pub fn config(&self) -> &SimConfig {
+        &self.config
+    }
 
+    /// Whether updating the sim any further would change any cell
+    ///
+    /// A cell is done once it has escaped and been stepped past `step_radius_sqr`, been found to
+    /// be inside the set (see `GridCell::is_interior`), or run `max_iters` iterations. So a render
+    /// loop can be as simple as `while !sim.is_done() { sim.update(); }`.
+    ///
+    /// Only `max_iters` guarantees this ever becomes true. Interior cells whose orbits aren't caught
+    /// by the period check keep iterating until they hit it, so with an effectively unbounded
+    /// `max_iters` (like `u32::MAX`), this may never return true for frames that show the set.
+    pub fn is_done(&self) -> bool {
         #[cfg(feature = "rayon")]
         {
-            fb.par_iter_mut().enumerate().for_each(|(i, pixel)| {
-                let mut c = color(&self.grid[i]);
-                // Clamp and scale all output from `color` into the range for our 8-bit channels: [0, 255]
-                c.clamp(DVec3::new(0., 0., 0.), DVec3::new(1., 1., 1.));
-                c *= 255.;
+            self.grid.par_iter().all(GridCell::is_done)
+        }
 
-                *pixel = rgb(c.x as u8, c.y as u8, c.z as u8);
-            });
+        #[cfg(not(feature = "rayon"))]
+        {
+            self.grid.iter().all(GridCell::is_done)
+        }
+    }
+
+    /// Count the cells that would still change if the sim were updated
+    pub fn active_count(&self) -> u32 {
+        #[cfg(feature = "rayon")]
+        {
+            self.grid.par_iter().filter(|cell| !cell.is_done()).count() as u32
         }
 
         #[cfg(not(feature = "rayon"))]
         {
-            for (i, pixel) in fb.iter_mut().enumerate() {
-                let mut c = color(&self.grid[i]);
-                // Clamp and scale all output from `color` into the range for our 8-bit channels: [0, 255]
-                c.clamp(DVec3::new(0., 0., 0.), DVec3::new(1., 1., 1.));
-                c *= 255.;
+            self.grid.iter().filter(|cell| !cell.is_done()).count() as u32
+        }
+    }
 
-                *pixel = rgb(c.x as u8, c.y as u8, c.z as u8);
-            }
+    /// Update the sim until `is_done`, or until it's been updated `max_steps` times
+    ///
+    /// Returns how many times the sim was updated.
+    pub fn run_until_done(&mut self, max_steps: u32) -> u32 {
+        let mut steps = 0;
+        let mut done = self.is_done();
+        while steps < max_steps && !done {
+            done = self.update().active == 0;
+            steps += 1;
         }
+
+        steps
     }
-}
 
-/// Make a square frame centered at `p` with radius `r`
-pub fn make_square_frame(p: DVec2, r: f64) -> (DVec2, DVec2) {
-    let min: DVec2 = DVec2::new(p.x - r, p.y - r);
-    let max: DVec2 = DVec2::new(p.x + r, p.y + r);
+    /// Like `run_until_done`, but also count the active cells after every step
+    ///
+    /// Returns the number of active cells (see `active_count`) after each update, so its length is
+    /// the number of updates. Cells never become active again once they're done, so the counts never
+    /// increase.
+    pub fn run_until_done_profiled(&mut self, max_steps: u32) -> Vec<u32> {
+        let mut counts = vec![];
+        let mut active = self.active_count();
 
-    (min, max)
-}
+        while (counts.len() as u32) < max_steps && active > 0 {
+            active = self.update().active;
+            counts.push(active);
+        }
 
-/// Makes a frame that shows "The" Mandelbrot fractal how everyone expects it
-pub fn make_default_frame() -> (DVec2, DVec2) {
-    let min: DVec2 = DVec2::new(-2.5, -1.25);
-    let max: DVec2 = DVec2::new(1.0, 1.25);
+        counts
+    }
 
-    (min, max)
+    /// Like `run_until_done`, but call `progress` after every update
+    ///
+    /// `progress` gets the index of the update, starting from `0`, and its stats, so a progress bar
+    /// can show the share of cells that are done. It's called between updates, never from inside
+    /// one, so `update` itself stays free of it. Returns how many times the sim was updated.
+    pub fn update_with_progress(
+        &mut self,
+        max_steps: u32,
+        mut progress: impl FnMut(u32, UpdateStats),
+    ) -> u32 {
+        let mut steps = 0;
+        let mut done = self.is_done();
+        while steps < max_steps && !done {
+            let stats = self.update();
+            progress(steps, stats);
+
+            done = stats.active == 0;
+            steps += 1;
+        }
+
+        steps
+    }
+
+    /// The squared radius that palettes should treat as escaped when coloring
+    pub fn coloring_bailout(&self) -> f64 {
+        self.coloring_r2
+    }
+
+    /// Change the squared radius that palettes treat as escaped when coloring
+    ///
+    /// The smooth iteration count of a cell that escaped past radius `R` after `n` iterations is
+    /// `n + 1 - log_p(ln|z_n| / ln R)`, for power `p`, and it's the same for any `n` past `R`. So the
+    /// `draw` functions hand palettes cells whose `smooth_iters` are shifted from counting at
+    /// `step_radius_sqr` to counting at `r_sq`, which moves the smooth palettes' colors along
+    /// without re-iterating anything.
+    ///
+    /// Cells are stepped until `|z|^2` passes `SimConfig::step_radius_sqr`, so any coloring bailout
+    /// up to that already has the `z` it needs, and only needs a redraw to take effect. That's the
+    /// precondition: `r_sq` must be no larger than `step_radius_sqr`, and no smaller than the escape
+    /// radius of `4`.
+    ///
+    /// Returns whether the new bailout was accepted. If `r_sq` is too large, the cells would need to
+    /// be re-iterated further than they're stepped, so the bailout is left unchanged.
+    pub fn set_coloring_bailout(&mut self, r_sq: f64) -> bool {
+        if !(4. ..=self.config.step_radius_sqr).contains(&r_sq) {
+            return false;
+        }
+
+        let power = self.config.power.max(2) as f64;
+        self.coloring_r2 = r_sq;
+        self.smooth_offset = (r_sq.ln() / self.config.step_radius_sqr.ln()).ln() / power.ln();
+        self.mark_all_dirty();
+        true
+    }
+
+    /// Change the dimensions of the framebuffer, keeping the same frame
+    ///
+    /// This resets the sim, and every cell is re-seeded for the new dimensions. Unlike making a new
+    /// `Sim`, the grid's allocation and the coloring bailout are kept.
+    pub fn resize(&mut self, new_dims: UVec2) {
+        self.config.fb_dims = new_dims;
+        self.reseed();
+    }
+
+    /// Change the dimensions of the framebuffer and the frame together
+    ///
+    /// This is `resize` followed by `set_frame`, but re-seeds the grid only once.
+    pub fn resize_to_frame(&mut self, new_dims: UVec2, frame_min: DVec2, frame_max: DVec2) {
+        self.config.fb_dims = new_dims;
+        self.config.frame_min = frame_min;
+        self.config.frame_max = frame_max;
+        self.reseed();
+    }
+
+    /// Move the sim to view a new frame, keeping `fb_dims`
+    ///
+    /// This resets the sim.
+    pub fn set_frame(&mut self, frame_min: DVec2, frame_max: DVec2) {
+        self.config.frame_min = frame_min;
+        self.config.frame_max = frame_max;
+        self.reseed();
+    }
+
+    /// Every cell in the grid, in rows of `grid_dims`
+    ///
+    /// Without supersampling, this is laid out exactly like the framebuffer.
+    pub fn cells(&self) -> &[GridCell<F>] {
+        &self.grid
+    }
+
+    /// The cell at `(x, y)` of the grid, or `None` if that's outside of `grid_dims`
+    ///
+    /// Without supersampling, this is the cell for the pixel at `(x, y)`.
+    pub fn cell_at(&self, x: u32, y: u32) -> Option<&GridCell<F>> {
+        let dims = self.config.grid_dims();
+        if x >= dims.x || y >= dims.y {
+            return None;
+        }
+
+        self.grid.get((y * dims.x + x) as usize)
+    }
+
+    /// Switch to iterating a different fractal, keeping the frame
+    ///
+    /// This resets the sim.
+    pub fn set_kind(&mut self, kind: FractalKind) {
+        self.config.kind = kind;
+        self.reseed();
+    }
+
+    /// Step every cell once, and report how many are still active and how many just escaped
+    ///
+    /// Once `active` reaches zero, further updates won't change anything, so render loops can stop.
+    ///
+    /// Mandelbrot frames with `frame_min.y == -frame_max.y` are mirror images of themselves across
+    /// the real axis, so for the standard formula, only the top half of the grid is stepped, and
+    /// each step is reflected onto the bottom half. That's about half the work, and the grid still
+    /// holds every cell, so drawing and everything else works like it would otherwise. Only rows
+    /// whose points are exact conjugates of their partners' are reflected, so the cells come out
+    /// exactly as if each had been stepped on its own. Sims made `with_seeds` are never mirrored.
+    pub fn update(&mut self) -> UpdateStats {
+        self.in_pool(Self::step_grid)
+    }
+
+    /// Run `op` on this sim inside of its thread pool, if it has one
+    fn in_pool<R: Send>(&mut self, op: impl FnOnce(&mut Self) -> R + Send) -> R {
+        #[cfg(feature = "rayon")]
+        if let Some(pool) = self.pool.take() {
+            let result = pool.install(|| op(self));
+            self.pool = Some(pool);
+            return result;
+        }
+
+        op(self)
+    }
+
+    /// Like `in_pool`, for operations that only need to read the sim
+    #[cfg(feature = "rayon")]
+    fn in_pool_ref<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        match &self.pool {
+            Some(pool) => pool.install(op),
+            None => op(),
+        }
+    }
+
+    /// Step every cell once, like `update`, on whichever pool we're already in
+    fn step_grid(&mut self) -> UpdateStats {
+        let config = self.config;
+        let grid_dims = config.grid_dims();
+        if config.is_mirrored() && grid_dims.y >= 2 && self.pixel_grid && self.mirror_in_step {
+            return self.update_mirrored();
+        }
+
+        let step = |(cell, dirty): (&mut GridCell<F>, &mut bool)| {
+            UpdateStats::step_marking(cell, dirty, &config)
+        };
+
+        #[cfg(feature = "rayon")]
+        {
+            self.grid
+                .par_iter_mut()
+                .zip(self.dirty.par_iter_mut())
+                .map(step)
+                .reduce(UpdateStats::default, UpdateStats::merge)
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            self.grid
+                .iter_mut()
+                .zip(self.dirty.iter_mut())
+                .map(step)
+                .fold(UpdateStats::default(), UpdateStats::merge)
+        }
+    }
+
+    /// Like `update`, but only step the top half of a mirrored grid, and reflect it onto the bottom
+    fn update_mirrored(&mut self) -> UpdateStats {
+        let config = self.config;
+        let grid_dims = config.grid_dims();
+        let (width, height) = (grid_dims.x as usize, grid_dims.y as usize);
+
+        let (first, paired, middle, bottom) = split_mirrored(&mut self.grid, width, height);
+        let (dirty_first, dirty_paired, dirty_middle, dirty_bottom) =
+            split_mirrored(&mut self.dirty, width, height);
+
+        let step = |(cell, dirty): (&mut GridCell<F>, &mut bool)| {
+            UpdateStats::step_marking(cell, dirty, &config)
+        };
+
+        // A row's cells all share its `y`, so if its first cell samples the exact conjugate of its
+        // partner's, they all do. Rounding in the frame mapping misses that for some rows, and those
+        // are stepped on their own, so the results are the same as stepping every cell.
+        type Rows<'a, F> = (&'a mut [GridCell<F>], &'a mut [bool]);
+        let step_rows = |((row, dirty_row), (mirror_row, dirty_mirror_row)): (Rows<F>, Rows<F>)| {
+            let exact = row[0].c.conj() == mirror_row[0].c;
+            let cells = row.iter_mut().zip(dirty_row.iter_mut());
+            let mirrors = mirror_row.iter_mut().zip(dirty_mirror_row.iter_mut());
+
+            cells
+                .zip(mirrors)
+                .map(|((cell, dirty), (mirror, mirror_dirty))| {
+                    if !exact {
+                        return step((cell, dirty)).merge(step((mirror, mirror_dirty)));
+                    }
+                    // A cell and its reflection are done at the same time, so done pairs never
+                    // even read the bottom half
+                    if cell.is_done() {
+                        return UpdateStats::default();
+                    }
+
+                    let stats = UpdateStats::step_marking(cell, dirty, &config);
+                    let GridCell { c, dc, .. } = *mirror;
+                    *mirror = GridCell {
+                        c,
+                        dc,
+                        ..cell.conj()
+                    };
+                    *mirror_dirty = true;
+                    stats.merge(stats)
+                })
+                .fold(UpdateStats::default(), UpdateStats::merge)
+        };
+
+        #[cfg(feature = "rayon")]
+        {
+            let unpaired = first
+                .par_iter_mut()
+                .zip(dirty_first.par_iter_mut())
+                .chain(middle.par_iter_mut().zip(dirty_middle.par_iter_mut()))
+                .map(step)
+                .reduce(UpdateStats::default, UpdateStats::merge);
+
+            let rows = paired
+                .par_chunks_mut(width)
+                .zip(dirty_paired.par_chunks_mut(width));
+            let mirror_rows = bottom
+                .par_chunks_mut(width)
+                .zip(dirty_bottom.par_chunks_mut(width))
+                .rev();
+
+            rows.zip(mirror_rows)
+                .map(step_rows)
+                .reduce(UpdateStats::default, UpdateStats::merge)
+                .merge(unpaired)
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            let unpaired = first
+                .iter_mut()
+                .zip(dirty_first.iter_mut())
+                .chain(middle.iter_mut().zip(dirty_middle.iter_mut()))
+                .map(step)
+                .fold(UpdateStats::default(), UpdateStats::merge);
+
+            let rows = paired.chunks_mut(width).zip(dirty_paired.chunks_mut(width));
+            let mirror_rows = bottom
+                .chunks_mut(width)
+                .zip(dirty_bottom.chunks_mut(width))
+                .rev();
+
+            rows.zip(mirror_rows)
+                .map(step_rows)
+                .fold(UpdateStats::default(), UpdateStats::merge)
+                .merge(unpaired)
+        }
+    }
+
+    /// Update the sim `n` times in a row
+    pub fn update_n(&mut self, n: u32) {
+        for _ in 0..n {
+            self.update();
+        }
+    }
+
+    /// Update only the cells inside of `rect`
+    ///
+    /// `rect` is `(min, max)` in pixel coordinates, where `min` is inclusive and `max` is exclusive.
+    /// It's clipped to `fb_dims`. Each cell inside is stepped exactly like `update` would step it,
+    /// including every cell of a supersampled pixel.
+    pub fn update_region(&mut self, rect: (UVec2, UVec2)) {
+        let (min, max) = self.clip_rect(rect);
+        let (min, max) = (
+            min * self.config.samples_per_axis,
+            max * self.config.samples_per_axis,
+        );
+        let width = self.config.grid_dims().x as usize;
+        let config = self.config;
+        let cols = min.x as usize..max.x as usize;
+        self.mirror_in_step = false;
+        self.mark_all_dirty();
+
+        #[cfg(feature = "rayon")]
+        {
+            self.grid
+                .par_chunks_mut(width)
+                .skip(min.y as usize)
+                .take((max.y - min.y) as usize)
+                .for_each(|row| {
+                    for cell in row[cols.clone()].iter_mut() {
+                        cell.step(&config);
+                    }
+                });
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            for row in self
+                .grid
+                .chunks_mut(width)
+                .skip(min.y as usize)
+                .take((max.y - min.y) as usize)
+            {
+                for cell in row[cols.clone()].iter_mut() {
+                    cell.step(&config);
+                }
+            }
+        }
+    }
+
+    /// Clip a `(min, max)` pixel rectangle to `fb_dims`
+    fn clip_rect(&self, (min, max): (UVec2, UVec2)) -> (UVec2, UVec2) {
+        let dims = self.config.fb_dims;
+        let max = UVec2::new(max.x.min(dims.x), max.y.min(dims.y));
+        let min = UVec2::new(min.x.min(max.x), min.y.min(max.y));
+
+        (min, max)
+    }
+
+    /// Continue iterating every unfinished cell until it has run `new_max` iterations
+    ///
+    /// Cells that are already done, or have already run at least `new_max` iterations, are left
+    /// untouched. Escaped cells that are still being stepped towards the coloring threshold are
+    /// continued like any other.
+    ///
+    /// If `new_max` is larger than `max_iters`, it's raised to `new_max`, and cells that had
+    /// reached the old limit pick up where they left off.
+    ///
+    /// For a grid that has only been advanced with `update`, this produces the same cells as a
+    /// `reset` followed by `new_max` calls to `update`, but only spends time on cells that can
+    /// still change.
+    pub fn extend_iteration(&mut self, new_max: u32) {
+        self.config.max_iters = self.config.max_iters.max(new_max);
+        let config = self.config;
+        self.mark_all_dirty();
+
+        // Up to the cap, cells finish like they would in a fresh run: flagged `reached_max` if they
+        // get there, or stepped on past it towards the coloring threshold if they've escaped
+        let finish = new_max == config.max_iters;
+        let extend = move |cell: &mut GridCell<F>| {
+            if cell.iters < config.max_iters {
+                cell.reached_max = false;
+            }
+            while (finish || cell.iters < new_max) && !cell.is_done() {
+                cell.step(&config);
+            }
+        };
+
+        #[cfg(feature = "rayon")]
+        {
+            self.grid.par_iter_mut().for_each(extend);
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            self.grid.iter_mut().for_each(extend);
+        }
+    }
+}
+
+impl Sim {
+    /// Move the frame to tightly fit the boundary of the set that's visible in the current frame
+    ///
+    /// This samples the current frame with a coarse grid (`REFRAME_SAMPLES` along the longer axis,
+    /// run for `REFRAME_ITERS` iterations) and finds the bounding box of pixels whose iteration
+    /// gradient is at least `REFRAME_GRADIENT`. That box is grown by `REFRAME_MARGIN` of its size on
+    /// each side, and then widened to match the aspect ratio of `fb_dims`.
+    ///
+    /// If no boundary is visible, the frame is left alone. Otherwise, this resets the sim.
+    pub fn reframe_to_fit(&mut self) {
+        let fb_dims = self.config.fb_dims;
+        let ratio = fb_dims.x as f64 / fb_dims.y as f64;
+
+        let coarse_dims = fit_aspect_ratio(ratio, DVec2::broadcast(REFRAME_SAMPLES as f64));
+        let coarse_dims = UVec2::new(coarse_dims.x.max(1), coarse_dims.y.max(1));
+        let mut coarse = Sim::new(SimConfig {
+            fb_dims: coarse_dims,
+            samples_per_axis: 1,
+            ..self.config
+        });
+        coarse.update_n(REFRAME_ITERS);
+
+        // Find the boundary's bounding box in the complex plane
+        let mut min = DVec2::broadcast(f64::INFINITY);
+        let mut max = DVec2::broadcast(f64::NEG_INFINITY);
+        for (idx, &g) in coarse.gradient_magnitude().iter().enumerate() {
+            if g >= REFRAME_GRADIENT {
+                let c = coarse.config.idx_to_complex(idx as u32);
+                min = min.min_by_component(c.to_dvec2());
+                max = max.max_by_component(c.to_dvec2());
+            }
+        }
+
+        if min.x > max.x || min.y > max.y {
+            return;
+        }
+
+        // Add our margin, and widen one axis to match the framebuffer
+        let center = 0.5 * (min + max);
+        let mut half = (0.5 + REFRAME_MARGIN) * (max - min);
+        if half.x < half.y * ratio {
+            half.x = half.y * ratio;
+        } else {
+            half.y = half.x / ratio;
+        }
+
+        self.set_frame(center - half, center + half);
+    }
+
+    /// Number of pixels in the framebuffer, which the `draw` functions expect `fb` to match
+    #[inline]
+    fn fb_len(&self) -> usize {
+        (self.config.fb_dims.x * self.config.fb_dims.y) as usize
+    }
+
+    /// The cells that cover the pixel at framebuffer index `idx`
+    ///
+    /// That's a single cell, unless the sim is supersampled.
+    #[inline]
+    fn pixel_cells(&self, idx: usize) -> impl Iterator<Item = &GridCell> + '_ {
+        let samples = self.config.samples_per_axis as usize;
+        let fb_width = self.config.fb_dims.x as usize;
+        let grid_width = fb_width * samples;
+
+        let x = (idx % fb_width) * samples;
+        let y = (idx / fb_width) * samples;
+
+        (0..samples).flat_map(move |dy| {
+            let start = (y + dy) * grid_width + x;
+            self.grid[start..start + samples].iter()
+        })
+    }
+
+    /// Color `cell` with `palette`, as seen from the coloring bailout
+    #[inline]
+    fn cell_color<P>(&self, cell: &GridCell, palette: &P) -> DVec3
+    where
+        P: Palette + ?Sized,
+    {
+        if self.smooth_offset == 0. || !cell.has_escaped {
+            return palette.color(cell);
+        }
+
+        palette.color(&GridCell {
+            smooth_iters: cell.smooth_iters + self.smooth_offset,
+            ..*cell
+        })
+    }
+
+    /// Average `palette` over the cells that cover the pixel at framebuffer index `idx`
+    #[inline]
+    fn pixel_color<P>(&self, idx: usize, palette: &P) -> DVec3
+    where
+        P: Palette + ?Sized,
+    {
+        if self.config.samples_per_axis == 1 {
+            return self.cell_color(&self.grid[idx], palette);
+        }
+
+        let samples = self.config.samples_per_axis * self.config.samples_per_axis;
+        let sum = self.pixel_cells(idx).fold(DVec3::zero(), |sum, cell| {
+            sum + self.cell_color(cell, palette)
+        });
+
+        sum / samples as f64
+    }
+
+    /// Average `smooth_iters` over the escaped cells that cover the pixel at framebuffer index `idx`
+    ///
+    /// Returns `None` if none of them have escaped.
+    #[inline]
+    fn pixel_smooth_iters(&self, idx: usize) -> Option<f64> {
+        let (sum, escaped) = self
+            .pixel_cells(idx)
+            .filter(|cell| cell.has_escaped)
+            .fold((0., 0), |(sum, escaped), cell| {
+                (sum + cell.smooth_iters, escaped + 1)
+            });
+
+        (escaped > 0).then(|| sum / escaped as f64 + self.smooth_offset)
+    }
+
+    /// Color every pixel of `fb` with `palette`
+    ///
+    /// Any `Fn(&GridCell) -> DVec3` is a palette, so this takes `&palette::with_plain_colors` as
+    /// readily as a stateful `&dyn Palette`. When the sim is supersampled, each pixel is the average
+    /// of `palette` over its cells.
+    pub fn draw<P>(&self, fb: &mut [u32], palette: &P)
+    where
+        P: Palette + ?Sized,
+    {
+        assert_eq!(fb.len(), self.fb_len());
+
+        #[cfg(feature = "rayon")]
+        {
+            self.in_pool_ref(|| {
+                fb.par_iter_mut().enumerate().for_each(|(i, pixel)| {
+                    *pixel = encode(self.pixel_color(i, palette));
+                })
+            });
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            for (i, pixel) in fb.iter_mut().enumerate() {
+                *pixel = encode(self.pixel_color(i, palette));
+            }
+        }
+    }
+
+    /// Like `draw`, but only recolor the pixels whose cells may have changed since the last call
+    ///
+    /// `fb` must hold what the last `draw_dirty` drew, with the same palette. Cells stepped by
+    /// `update` are tracked one by one, so once the grid settles down, or while it's paused, this
+    /// skips almost everything. Everything else that changes cells, like the other update methods,
+    /// `reset`, or `set_frame`, marks the whole grid as changed. Call `mark_all_dirty` when the
+    /// palette changes, or when `fb` has been drawn over, to recolor everything.
+    pub fn draw_dirty<P>(&mut self, fb: &mut [u32], palette: &P)
+    where
+        P: Palette + ?Sized,
+    {
+        assert_eq!(fb.len(), self.fb_len());
+
+        // A quick scan is much cheaper than walking every pixel, for when nothing changed at all
+        if !self.dirty.contains(&true) {
+            return;
+        }
+
+        let samples = self.config.samples_per_axis as usize;
+        let fb_width = self.config.fb_dims.x as usize;
+        let grid_width = fb_width * samples;
+        let dirty = &self.dirty;
+
+        // Same cells as `pixel_cells`
+        let is_dirty = |idx: usize| {
+            let x = (idx % fb_width) * samples;
+            let y = (idx / fb_width) * samples;
+
+            (0..samples).any(|dy| {
+                let start = (y + dy) * grid_width + x;
+                dirty[start..start + samples].contains(&true)
+            })
+        };
+
+        #[cfg(feature = "rayon")]
+        {
+            self.in_pool_ref(|| {
+                fb.par_iter_mut()
+                    .enumerate()
+                    .filter(|&(i, _)| is_dirty(i))
+                    .for_each(|(i, pixel)| *pixel = encode(self.pixel_color(i, palette)))
+            });
+            self.in_pool(|sim| sim.dirty.par_iter_mut().for_each(|dirty| *dirty = false));
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            for (i, pixel) in fb.iter_mut().enumerate() {
+                if is_dirty(i) {
+                    *pixel = encode(self.pixel_color(i, palette));
+                }
+            }
+            self.dirty.iter_mut().for_each(|dirty| *dirty = false);
+        }
+    }
+
+    /// Draw the grid with `color`, and return it as tightly packed RGBA8 bytes
+    ///
+    /// Rows are in the same order as `draw`'s framebuffer, top row first, so there are
+    /// `4 * fb_dims.x * fb_dims.y` bytes. Alpha is always `0xFF`. This doesn't need a window or the
+    /// `image` feature, so it's the way to render for headless callers.
+    pub fn render_rgba<ColorFn>(&mut self, color: ColorFn) -> Vec<u8>
+    where
+        ColorFn: Fn(&GridCell) -> DVec3 + Sync,
+    {
+        let mut fb = std::mem::take(&mut self.scratch_fb);
+        fb.resize(self.fb_len(), 0);
+
+        self.draw(&mut fb, &color);
+        let rgba = export::to_rgba(&fb);
+
+        self.scratch_fb = fb;
+        rgba
+    }
+
+    /// Like `render_rgba`, but with 16 bits per channel, so smooth gradients don't band
+    ///
+    /// There are `4 * fb_dims.x * fb_dims.y` channels, and alpha is always `0xFFFF`. Colors are
+    /// clamped like `draw`, and non-finite colors are still `NON_FINITE_COLOR`. Save it with
+    /// `save_png16`.
+    pub fn render_rgba16<ColorFn>(&self, color: ColorFn) -> Vec<u16>
+    where
+        ColorFn: Fn(&GridCell) -> DVec3 + Sync,
+    {
+        let pixel = |i: usize| encode16(self.pixel_color(i, &color));
+
+        #[cfg(feature = "rayon")]
+        let pixels: Vec<[u16; 4]> = (0..self.fb_len()).into_par_iter().map(pixel).collect();
+
+        #[cfg(not(feature = "rayon"))]
+        let pixels: Vec<[u16; 4]> = (0..self.fb_len()).map(pixel).collect();
+
+        pixels.concat()
+    }
+
+    /// Like `draw`, but adjust how colors are encoded with `options`
+    ///
+    /// With the default options, this draws exactly like `draw`.
+    pub fn draw_with_options<ColorFn>(&self, fb: &mut [u32], color: ColorFn, options: &DrawOptions)
+    where
+        ColorFn: Fn(&GridCell) -> DVec3 + Sync,
+    {
+        assert_eq!(fb.len(), self.fb_len());
+
+        let width = self.config.fb_dims.x;
+        let inv_gamma = 1. / options.gamma;
+        let shade = |i: usize| {
+            let mut c = self.pixel_color(i, &color);
+            if options.gamma != 1. {
+                // Leave negative and NaN channels alone, for `encode` to deal with
+                let correct = |x: f64| if x > 0. { x.powf(inv_gamma) } else { x };
+                c = DVec3::new(correct(c.x), correct(c.y), correct(c.z));
+            }
+
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            encode_dithered(c, options.noise.threshold(x, y))
+        };
+
+        #[cfg(feature = "rayon")]
+        {
+            fb.par_iter_mut().enumerate().for_each(|(i, pixel)| {
+                *pixel = shade(i);
+            });
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            for (i, pixel) in fb.iter_mut().enumerate() {
+                *pixel = shade(i);
+            }
+        }
+    }
+
+    /// Draw escaped cells by the rank of their iteration count, sampling `gradient`
+    ///
+    /// This is histogram coloring. The first pass counts how many escaped cells finished at each
+    /// iteration count, and the second colors each cell by the fraction of escaped cells that took no
+    /// more iterations than it did. That spreads the gradient evenly over the escaped pixels, however
+    /// bunched up their counts are at this zoom. Cells that haven't escaped are black.
+    pub fn draw_histogram(&self, fb: &mut [u32], gradient: &palette::Gradient) {
+        let escaped = || self.grid.iter().filter(|cell| cell.has_escaped);
+
+        let max_iters = escaped().map(|cell| cell.iters).max().unwrap_or(0);
+        let mut cdf: Vec<u32> = vec![0; max_iters as usize + 1];
+        for cell in escaped() {
+            cdf[cell.iters as usize] += 1;
+        }
+        for i in 1..cdf.len() {
+            cdf[i] += cdf[i - 1];
+        }
+        let total = cdf[cdf.len() - 1].max(1) as f64;
+
+        let color = |cell: &GridCell| {
+            if cell.has_escaped {
+                gradient.sample(cdf[cell.iters as usize] as f64 / total)
+            } else {
+                DVec3::broadcast(0.)
+            }
+        };
+        self.draw(fb, &color);
+    }
+
+    /// Draw with a lookup table of colors by iteration count, built with `iter_to_color_lut`
+    ///
+    /// Escaped cells are colored `lut[iters]`, clamped to the last iteration entry, and cells that
+    /// haven't escaped get the final entry. This is only a single index per pixel, which skips the
+    /// palette's work and the encoding of every pixel (`benches/draw.rs` compares the two) - but it
+    /// only works for palettes that depend solely on `iters`, and not on `z` or `dz`.
+    pub fn draw_with_lut(&self, fb: &mut [u32], lut: &[u32]) {
+        assert_eq!(fb.len(), self.fb_len());
+        assert!(
+            lut.len() >= 2,
+            "Expected at least one iteration entry and an interior entry"
+        );
+
+        let interior = lut.len() - 1;
+        let lookup = |cell: &GridCell| {
+            if cell.has_escaped {
+                lut[(cell.iters as usize).min(interior - 1)]
+            } else {
+                lut[interior]
+            }
+        };
+
+        // Supersampled pixels average their cells' entries, channel by channel
+        let samples = self.config.samples_per_axis * self.config.samples_per_axis;
+        let lookup = |i: usize| {
+            if samples == 1 {
+                return lookup(&self.grid[i]);
+            }
+
+            let mut sum = [0; 3];
+            for cell in self.pixel_cells(i) {
+                let [_, r, g, b] = lookup(cell).to_be_bytes();
+                sum[0] += r as u32;
+                sum[1] += g as u32;
+                sum[2] += b as u32;
+            }
+
+            rgb(
+                (sum[0] / samples) as u8,
+                (sum[1] / samples) as u8,
+                (sum[2] / samples) as u8,
+            )
+        };
+
+        #[cfg(feature = "rayon")]
+        {
+            fb.par_iter_mut().enumerate().for_each(|(i, pixel)| {
+                *pixel = lookup(i);
+            });
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            for (i, pixel) in fb.iter_mut().enumerate() {
+                *pixel = lookup(i);
+            }
+        }
+    }
+
+    /// Draw a preview of a render in progress
+    ///
+    /// Cells that are done are drawn with `color`, and cells that are still active (see
+    /// `active_count`) are drawn in gray. Returns a framebuffer of `fb_dims`.
+    pub fn progress_image<ColorFn>(&self, color: ColorFn) -> Vec<u32>
+    where
+        ColorFn: Fn(&GridCell) -> DVec3 + Sync,
+    {
+        const ACTIVE_COLOR: u32 = rgb(128, 128, 128);
+
+        let mut framebuffer = vec![ACTIVE_COLOR; self.fb_len()];
+        self.draw_masked(&mut framebuffer, color, GridCell::is_done);
+
+        framebuffer
+    }
+
+    /// Draw only the pixels whose cells all satisfy `mask`, leaving the rest of `fb` as-is
+    ///
+    /// This makes it easy to layer palettes, e.g. draw the exterior with one palette and then the
+    /// interior with another.
+    pub fn draw_masked<ColorFn, P>(&self, fb: &mut [u32], color: ColorFn, mask: P)
+    where
+        ColorFn: Fn(&GridCell) -> DVec3 + Sync,
+        P: Fn(&GridCell) -> bool + Sync,
+    {
+        assert_eq!(fb.len(), self.fb_len());
+
+        #[cfg(feature = "rayon")]
+        {
+            fb.par_iter_mut()
+                .enumerate()
+                .filter(|(i, _)| self.pixel_cells(*i).all(&mask))
+                .for_each(|(i, pixel)| {
+                    *pixel = encode(self.pixel_color(i, &color));
+                });
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            for (i, pixel) in fb.iter_mut().enumerate() {
+                if self.pixel_cells(i).all(&mask) {
+                    *pixel = encode(self.pixel_color(i, &color));
+                }
+            }
+        }
+    }
+
+    /// Draw only the pixels inside of `rect`, leaving the rest of `fb` as-is
+    ///
+    /// `rect` is interpreted like it is for `update_region`.
+    pub fn draw_region<ColorFn>(&self, fb: &mut [u32], color: ColorFn, rect: (UVec2, UVec2))
+    where
+        ColorFn: Fn(&GridCell) -> DVec3 + Sync,
+    {
+        assert_eq!(fb.len(), self.fb_len());
+
+        let (min, max) = self.clip_rect(rect);
+        let width = self.config.fb_dims.x as usize;
+        let cols = min.x as usize..max.x as usize;
+
+        let draw_row = |(y, fb_row): (usize, &mut [u32])| {
+            for x in cols.clone() {
+                fb_row[x] = encode(self.pixel_color(y * width + x, &color));
+            }
+        };
+
+        #[cfg(feature = "rayon")]
+        {
+            fb.par_chunks_mut(width)
+                .enumerate()
+                .skip(min.y as usize)
+                .take((max.y - min.y) as usize)
+                .for_each(draw_row);
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            fb.chunks_mut(width)
+                .enumerate()
+                .skip(min.y as usize)
+                .take((max.y - min.y) as usize)
+                .for_each(draw_row);
+        }
+    }
+
+    /// Count how often each 8-bit value appears in each channel of the image `draw` would produce
+    ///
+    /// Returns `[red, green, blue]` histograms, each indexed by channel value.
+    pub fn color_histogram<ColorFn>(&self, color: ColorFn) -> [[u32; 256]; 3]
+    where
+        ColorFn: Fn(&GridCell) -> DVec3 + Sync,
+    {
+        let len = self.fb_len();
+
+        let histogram = |pixels: std::ops::Range<usize>| {
+            let mut hist = [[0; 256]; 3];
+            for i in pixels {
+                let [_, r, g, b] = encode(self.pixel_color(i, &color)).to_be_bytes();
+                hist[0][r as usize] += 1;
+                hist[1][g as usize] += 1;
+                hist[2][b as usize] += 1;
+            }
+            hist
+        };
+
+        #[cfg(feature = "rayon")]
+        {
+            // Bin chunks of pixels separately, so we're not copying histograms around for every pixel
+            const CHUNK: usize = 4096;
+            let chunk = |n: usize| n * CHUNK..((n + 1) * CHUNK).min(len);
+            (0..len.div_ceil(CHUNK))
+                .into_par_iter()
+                .map(chunk)
+                .map(histogram)
+                .reduce(
+                    || [[0; 256]; 3],
+                    |mut a, b| {
+                        for (a, b) in a.iter_mut().zip(b.iter()) {
+                            for (a, b) in a.iter_mut().zip(b.iter()) {
+                                *a += b;
+                            }
+                        }
+                        a
+                    },
+                )
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            histogram(0..len)
+        }
+    }
+
+    /// Draw into a framebuffer of `dims` by upscaling the smooth iteration count, then coloring it
+    ///
+    /// The smooth iteration count of each pixel, like `iteration_buffer`'s, is interpolated with
+    /// `bicubic_upscale_f32` before `color` sees it, so a coarse grid produces a smooth image without
+    /// blending colors together. Note that `color` only sees the count, and pixels that haven't
+    /// escaped report the number of steps run so far instead, so the field stays finite.
+    pub fn draw_upscaled<ColorFn>(&self, fb: &mut [u32], dims: UVec2, color: ColorFn)
+    where
+        ColorFn: Fn(f32) -> DVec3 + Sync,
+    {
+        assert_eq!(fb.len(), (dims.x * dims.y) as usize);
+
+        let field: Vec<f32> = (0..self.fb_len())
+            .map(|idx| {
+                self.pixel_smooth_iters(idx).unwrap_or_else(|| {
+                    let steps = self.pixel_cells(idx).map(|cell| cell.iters).max();
+                    steps.unwrap_or(0) as f64
+                }) as f32
+            })
+            .collect();
+        let field = bicubic_upscale_f32(&field, self.config.fb_dims, dims);
+
+        #[cfg(feature = "rayon")]
+        {
+            fb.par_iter_mut().zip(field).for_each(|(pixel, v)| {
+                *pixel = encode(color(v));
+            });
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            for (pixel, v) in fb.iter_mut().zip(field) {
+                *pixel = encode(color(v));
+            }
+        }
+    }
+
+    /// Find the component-wise range of the colors `color` produces across every cell
+    ///
+    /// Colors are measured raw, before `draw` clamps them into `[0, 1]`, so this can be used to check
+    /// whether a palette is well-behaved over a frame. Returns `(min, max)`.
+    pub fn palette_range<ColorFn>(&self, color: ColorFn) -> (DVec3, DVec3)
+    where
+        ColorFn: Fn(&GridCell) -> DVec3 + Sync,
+    {
+        let empty = (
+            DVec3::broadcast(f64::INFINITY),
+            DVec3::broadcast(f64::NEG_INFINITY),
+        );
+
+        #[cfg(feature = "rayon")]
+        {
+            self.grid
+                .par_iter()
+                .map(|cell| {
+                    let c = self.cell_color(cell, &color);
+                    (c, c)
+                })
+                .reduce(
+                    || empty,
+                    |(a_min, a_max), (b_min, b_max)| {
+                        (a_min.min_by_component(b_min), a_max.max_by_component(b_max))
+                    },
+                )
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            self.grid.iter().fold(empty, |(min, max), cell| {
+                let c = self.cell_color(cell, &color);
+                (min.min_by_component(c), max.max_by_component(c))
+            })
+        }
+    }
+
+    /// Accumulate how often escaping orbits visit each pixel of the frame
+    ///
+    /// This samples `samples` random points `c` inside the frame, and for each one that escapes,
+    /// counts every point of its orbit that also lands inside the frame. It's a "local" Buddhabrot:
+    /// only the frame is sampled, so it shows where orbits concentrate within the current view.
+    ///
+    /// The result is laid out like the framebuffer.
+    pub fn render_local_orbit_density(&self, samples: u32) -> Vec<u32> {
+        let config = self.config;
+        let size = (config.fb_dims.x * config.fb_dims.y) as usize;
+
+        let accumulate = |density: &mut Vec<u32>, rng: &mut rand::rngs::ThreadRng| {
+            use rand::Rng;
+
+            // Not `gen_range`, which panics on the empty range of a zero-width or inverted frame
+            let t = DVec2::new(rng.gen(), rng.gen());
+            let c = (config.frame_min + t * (config.frame_max - config.frame_min)).to_complex();
+
+            let orbit = compute_orbit(c, ORBIT_ITERS);
+            let escaped = orbit.last().is_some_and(|z| z.norm_sqr() > 4.0);
+            if !escaped {
+                return;
+            }
+
+            for z in orbit {
+                if let Some(p) = config.complex_to_pixel(z) {
+                    density[(p.y * config.fb_dims.x + p.x) as usize] += 1;
+                }
+            }
+        };
+
+        #[cfg(feature = "rayon")]
+        {
+            (0..samples)
+                .into_par_iter()
+                .fold(
+                    || vec![0; size],
+                    |mut density, _| {
+                        accumulate(&mut density, &mut rand::thread_rng());
+                        density
+                    },
+                )
+                .reduce(
+                    || vec![0; size],
+                    |mut a, b| {
+                        for (a, b) in a.iter_mut().zip(b) {
+                            *a += b;
+                        }
+                        a
+                    },
+                )
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            let mut density = vec![0; size];
+            let mut rng = rand::thread_rng();
+            for _ in 0..samples {
+                accumulate(&mut density, &mut rng);
+            }
+            density
+        }
+    }
+
+    /// Render the full grid with `color`, then shrink it to a thumbnail
+    ///
+    /// The thumbnail keeps the aspect ratio of `fb_dims` and fits within `max_dim` in both
+    /// dimensions. It's never larger than the full render. Returns the thumbnail and its dimensions.
+    pub fn render_thumbnail<ColorFn>(&mut self, color: ColorFn, max_dim: u32) -> (Vec<u32>, UVec2)
+    where
+        ColorFn: Fn(&GridCell) -> DVec3 + Sync,
+    {
+        let fb_dims = self.config.fb_dims;
+        let mut framebuffer: Vec<u32> = vec![0; (fb_dims.x * fb_dims.y) as usize];
+        self.draw(&mut framebuffer, &color);
+
+        let ratio = fb_dims.x as f64 / fb_dims.y as f64;
+        let thumb_dims = fit_aspect_ratio(ratio, DVec2::broadcast(max_dim as f64));
+        let thumb_dims = UVec2::new(
+            thumb_dims.x.clamp(1, fb_dims.x),
+            thumb_dims.y.clamp(1, fb_dims.y),
+        );
+
+        (downsample(&framebuffer, fb_dims, thumb_dims), thumb_dims)
+    }
+
+    /// Run-length encode the iteration count of every cell, in the order of `cells`
+    ///
+    /// Returns `(iters, count)` pairs, where each run is `count` consecutive cells that all took
+    /// `iters` iterations. The exterior of the set has long runs of equal counts along each scanline,
+    /// and the interior is one big run, so typical frames shrink a lot. Decode with
+    /// `decode_iters_rle`.
+    pub fn iters_rle(&self) -> Vec<(u32, u32)> {
+        let mut runs: Vec<(u32, u32)> = vec![];
+
+        for cell in &self.grid {
+            match runs.last_mut() {
+                Some((iters, count)) if *iters == cell.iters => *count += 1,
+                _ => runs.push((cell.iters, 1)),
+            }
+        }
+
+        runs
+    }
+
+    /// Compute the magnitude of the gradient of the iteration count at each pixel
+    ///
+    /// This is `sqrt(dx^2 + dy^2)` using central differences over each pixel's neighbors, in units
+    /// of iterations per pixel. Pixels on the border use one-sided differences instead. The result is
+    /// laid out like the framebuffer, or like `grid_dims` for a supersampled sim, where it's per cell.
+    ///
+    /// Smooth regions report values near zero, while the boundary of the set reports large values.
+    pub fn gradient_magnitude(&self) -> Vec<f32> {
+        let dims = self.config.grid_dims();
+        let iters = |x: u32, y: u32| self.grid[(y * dims.x + x) as usize].iters as f32;
+
+        // Central difference of `f` around `i`, falling back to one-sided at the edges
+        fn diff(i: u32, len: u32, f: impl Fn(u32) -> f32) -> f32 {
+            let lo = i.saturating_sub(1);
+            let hi = (i + 1).min(len - 1);
+
+            if hi == lo {
+                0.
+            } else {
+                (f(hi) - f(lo)) / (hi - lo) as f32
+            }
+        }
+
+        let gradient = |idx: usize| {
+            let x = idx as u32 % dims.x;
+            let y = idx as u32 / dims.x;
+
+            let dx = diff(x, dims.x, |x| iters(x, y));
+            let dy = diff(y, dims.y, |y| iters(x, y));
+
+            (dx * dx + dy * dy).sqrt()
+        };
+
+        #[cfg(feature = "rayon")]
+        {
+            (0..self.grid.len()).into_par_iter().map(gradient).collect()
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            (0..self.grid.len()).map(gradient).collect()
+        }
+    }
+
+    /// Render the signed distance from each pixel to the boundary of the set
+    ///
+    /// The result is laid out like the framebuffer, one value per pixel of `fb_dims`. Distances are
+    /// in complex-plane units (not pixels), so they stay meaningful across zoom levels.
+    ///
+    /// The sign convention is negative inside the set and positive outside:
+    /// - Escaped cells use the exterior distance estimate from `GridCell::distance_estimate`.
+    /// - Cells that haven't escaped (yet) are treated as inside. There is no interior estimate,
+    ///   so they report `f32::NEG_INFINITY`.
+    /// - Escaped cells whose estimate is unavailable (e.g. `dz == 0`) report `f32::NAN`, so they
+    ///   can't be mistaken for cells on the boundary.
+    ///
+    /// When the sim is supersampled, a pixel that any of its cells escaped from is outside, at the
+    /// average of their estimates, like `iteration_buffer`. It's only `f32::NAN` if none of those
+    /// cells has an estimate.
+    pub fn render_sdf(&self) -> Vec<f32> {
+        let signed_distance = |idx: usize| {
+            let (sum, estimated, escaped) =
+                self.pixel_cells(idx).filter(|cell| cell.has_escaped).fold(
+                    (0., 0, 0),
+                    |(sum, estimated, escaped), cell| match cell.distance_estimate() {
+                        Some(d) => (sum + d, estimated + 1, escaped + 1),
+                        None => (sum, estimated, escaped + 1),
+                    },
+                );
+
+            if escaped == 0 {
+                f32::NEG_INFINITY
+            } else if estimated == 0 {
+                f32::NAN
+            } else {
+                (sum / estimated as f64) as f32
+            }
+        };
+
+        #[cfg(feature = "rayon")]
+        {
+            (0..self.fb_len())
+                .into_par_iter()
+                .map(signed_distance)
+                .collect()
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            (0..self.fb_len()).map(signed_distance).collect()
+        }
+    }
+
+    /// The smooth iteration count of every pixel, laid out like the framebuffer
+    ///
+    /// This is the scalar field that the smooth palettes color, for post-processing without going
+    /// through a palette. Pixels that haven't escaped (yet) are `f32::INFINITY`, since smooth
+    /// counts can dip below zero. When the sim is supersampled, each pixel is the average over its
+    /// escaped cells, and is only `f32::INFINITY` if none of them escaped.
+    pub fn iteration_buffer(&self) -> Vec<f32> {
+        let smooth_iters = |idx: usize| {
+            self.pixel_smooth_iters(idx)
+                .map_or(f32::INFINITY, |smooth_iters| smooth_iters as f32)
+        };
+
+        #[cfg(feature = "rayon")]
+        {
+            (0..self.fb_len())
+                .into_par_iter()
+                .map(smooth_iters)
+                .collect()
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            (0..self.fb_len()).map(smooth_iters).collect()
+        }
+    }
+}
+
+/// Compute the orbit of `0` under `z = z^2 + c`
+///
+/// The orbit stops after the first point that escapes (`|z| > 2`), or after `max_iters` points.
+/// The starting `z = 0` is not included.
+pub fn compute_orbit(c: Complex<f64>, max_iters: u32) -> Vec<Complex<f64>> {
+    let mut orbit = vec![];
+    let mut z = Complex::new(0., 0.);
+
+    for _ in 0..max_iters {
+        z = z * z + c;
+        orbit.push(z);
+
+        if z.norm_sqr() > 4.0 {
+            break;
+        }
+    }
+
+    orbit
+}
+
+/// Expand runs from `Sim::iters_rle` back into one iteration count per cell
+pub fn decode_iters_rle(runs: &[(u32, u32)]) -> Vec<u32> {
+    runs.iter()
+        .flat_map(|&(iters, count)| std::iter::repeat_n(iters, count as usize))
+        .collect()
+}
+
+/// Split rows of a mirrored grid (see `Sim::update_mirrored`) into the parts that step differently
+///
+/// Row `y` samples the conjugate of row `height - y`. So row 0 has no partner in the grid, and
+/// neither does the middle row of an even grid, which is its own reflection. Returns the first row,
+/// the rows of the top half that have partners, the middle row if there is one, and the bottom half,
+/// whose rows are the partners in reverse order.
+fn split_mirrored<T>(
+    rows: &mut [T],
+    width: usize,
+    height: usize,
+) -> (&mut [T], &mut [T], &mut [T], &mut [T]) {
+    let (top, bottom) = rows.split_at_mut((height / 2 + 1) * width);
+    let (first, rest) = top.split_at_mut(width);
+    let (paired, middle) = rest.split_at_mut(bottom.len());
+
+    (first, paired, middle, bottom)
+}
+
+/// Whether `c` is inside the main cardioid or the period-2 bulb of the Mandelbrot set
+///
+/// Together these cover most of the interior of the set, and both have closed forms, so points
+/// inside them can skip iterating entirely.
+fn in_main_bulbs(c: Complex<f64>) -> bool {
+    let (x, y) = (c.re, c.im);
+
+    // Main cardioid
+    let q = (x - 0.25) * (x - 0.25) + y * y;
+    if q * (q + (x - 0.25)) <= 0.25 * y * y {
+        return true;
+    }
+
+    // Period-2 bulb, the disk of radius 1/4 around -1
+    (x + 1.) * (x + 1.) + y * y <= 1. / 16.
+}
+
+/// Build a lookup table for `Sim::draw_with_lut`
+///
+/// Entry `i` for `i` in `0..=max_iters` is `color(i)`, the color of a cell that escaped after `i`
+/// iterations, and the final entry is `interior`, for cells that haven't escaped.
+pub fn iter_to_color_lut<ColorFn>(max_iters: u32, color: ColorFn, interior: DVec3) -> Vec<u32>
+where
+    ColorFn: Fn(u32) -> DVec3,
+{
+    (0..=max_iters)
+        .map(|iters| encode(color(iters)))
+        .chain(std::iter::once(encode(interior)))
+        .collect()
+}
+
+/// Render an animation of a Julia set morphing from the constant `c0` to `c1`
+///
+/// Each of the `frames` frames linearly interpolates `c` from `c0` to `c1`, inclusive, runs for
+/// `steps` iterations, and is saved as a PNG with `color`. Filenames come from `out_pattern`, with the
+/// first `{}` replaced by the zero-padded frame number, e.g. `"julia-{}.png"`. Only the `fb_dims`,
+/// frame, and transform of `config` are used.
+///
+/// Julia sets change most dramatically for `c` near the boundary of the Mandelbrot set, so paths that
+/// stay close to it make for the most interesting morphs. Deep inside or far outside, the frames all
+/// look like a blob or dust.
+#[cfg(feature = "image")]
+pub fn render_julia_morph<ColorFn>(
+    config: SimConfig,
+    c0: Complex<f64>,
+    c1: Complex<f64>,
+    frames: u32,
+    steps: u32,
+    color: ColorFn,
+    out_pattern: &str,
+) -> std::io::Result<()>
+where
+    ColorFn: Fn(&GridCell) -> DVec3 + Sync,
+{
+    let digits = frames.to_string().len();
+    let mut framebuffer: Vec<u32> = vec![0; (config.fb_dims.x * config.fb_dims.y) as usize];
+
+    for i in 0..frames {
+        let t = if frames > 1 {
+            i as f64 / (frames - 1) as f64
+        } else {
+            0.
+        };
+
+        let config = SimConfig {
+            kind: FractalKind::Julia {
+                c: c0 * (1. - t) + c1 * t,
+            },
+            ..config
+        };
+        let mut sim = Sim::new(config);
+        sim.update_n(steps);
+        sim.draw(&mut framebuffer, &color);
+
+        let path = out_pattern.replacen("{}", &format!("{:0width$}", i, width = digits), 1);
+        save_png(path, &framebuffer, &config, "custom")?;
+    }
+
+    Ok(())
+}
+
+/// Estimate the external angle of `c`, in turns in the range `[0, 1)`
+///
+/// This is the argument of the Böttcher coordinate `phi(c)`, which has the product form
+/// `phi(c) = c * prod_k (1 + c / z_k^2) ^ (1 / 2^k)` over the orbit `z_1 = c, z_k+1 = z_k^2 + c`.
+/// Taking the argument of each factor turns the product into a sum of angles, which we accumulate
+/// until `|z|^2` passes `DEFAULT_STEP_RADIUS_SQR` and the remaining terms are negligible.
+///
+/// Returns `None` if `c` hasn't escaped after `max_iters` iterations.
+///
+/// Each term uses the principal branch of `arg`, which is only correct once `|c / z_k^2|` is small.
+/// Close to the boundary of the set, early terms can land on the wrong branch, and the estimate can be
+/// off by a multiple of `2^-k` turns. It's accurate for points comfortably outside the set.
+pub fn external_angle(c: Complex<f64>, max_iters: u32) -> Option<f64> {
+    let mut z = c;
+    let mut angle = c.arg();
+    let mut weight = 1.;
+
+    for _ in 0..max_iters {
+        if z.norm_sqr() > DEFAULT_STEP_RADIUS_SQR {
+            let turns = angle / std::f64::consts::TAU;
+            return Some(turns.rem_euclid(1.));
+        }
+
+        weight /= 2.;
+        angle += weight * (1. + c / (z * z)).arg();
+
+        z = z * z + c;
+    }
+
+    None
+}
+
+/// Draw the same grid with each of `palettes`, stacked top to bottom and labelled with their names
+///
+/// Each palette gets a full-size copy of the frame, so the sheet is `fb_dims.x` wide and
+/// `fb_dims.y * palettes.len()` tall.
+pub fn render_palette_sheet(sim: &Sim, palettes: &[palette::PaletteInfo]) -> Vec<u32> {
+    let fb_dims = sim.config.fb_dims;
+    let strip_len = (fb_dims.x * fb_dims.y) as usize;
+
+    // Scale the labels up with the image so they stay legible, but always draw at least 1:1
+    let scale = (fb_dims.y / 100).max(1);
+    let margin = UVec2::broadcast(2 * scale);
+
+    let mut sheet: Vec<u32> = vec![0; strip_len * palettes.len()];
+    for (info, strip) in palettes.iter().zip(sheet.chunks_exact_mut(strip_len)) {
+        sim.draw(strip, &info.color);
+        text::draw_label(strip, fb_dims, margin, info.name, scale);
+    }
+
+    sheet
+}
+
+/// Render each of `locations` as a captioned thumbnail, and tile them into one image
+///
+/// Each location is run for `steps` iterations, drawn with `color`, and shrunk to fit in
+/// `thumb_dim`x`thumb_dim`. Thumbnails are laid out in a roughly square grid, each with its caption
+/// underneath. Returns the gallery and its dimensions.
+pub fn render_gallery<ColorFn>(
+    locations: &[(SimConfig, &str)],
+    color: ColorFn,
+    steps: u32,
+    thumb_dim: u32,
+) -> (Vec<u32>, UVec2)
+where
+    ColorFn: Fn(&GridCell) -> DVec3 + Sync + Copy,
+{
+    let scale = (thumb_dim / 128).max(1);
+    let padding = 2 * scale;
+    let caption_height = text::GLYPH_HEIGHT * scale + 2 * padding;
+
+    let cols = (locations.len() as f64).sqrt().ceil().max(1.) as u32;
+    let rows = (locations.len() as u32).div_ceil(cols);
+    let cell_dims = UVec2::new(thumb_dim, thumb_dim + caption_height);
+    let dims = UVec2::new(cols * cell_dims.x, rows.max(1) * cell_dims.y);
+
+    let mut gallery: Vec<u32> = vec![0; (dims.x * dims.y) as usize];
+    for (i, (config, caption)) in locations.iter().enumerate() {
+        let mut sim = Sim::new(*config);
+        sim.update_n(steps);
+        let (thumb, thumb_dims) = sim.render_thumbnail(color, thumb_dim);
+
+        // Center each thumbnail horizontally in its cell
+        let origin = UVec2::new(
+            (i as u32 % cols) * cell_dims.x + (thumb_dim - thumb_dims.x) / 2,
+            (i as u32 / cols) * cell_dims.y,
+        );
+        for (y, row) in thumb.chunks_exact(thumb_dims.x as usize).enumerate() {
+            let start = ((origin.y + y as u32) * dims.x + origin.x) as usize;
+            gallery[start..start + row.len()].copy_from_slice(row);
+        }
+
+        let caption_pos = UVec2::new(
+            (i as u32 % cols) * cell_dims.x + padding,
+            origin.y + thumb_dim + padding,
+        );
+        text::draw_label(&mut gallery, dims, caption_pos, caption, scale);
+    }
+
+    (gallery, dims)
+}
+
+/// Pick the largest dimensions with the aspect ratio `ratio` (width / height) that fit in `bounds`
+pub fn fit_aspect_ratio(ratio: f64, bounds: DVec2) -> UVec2 {
+    // This is the ratio of the width of the bounds to the height
+    // Greater than 1.0 is typical, and means the bounds are wider than they are tall.
+    let bounds_ratio = bounds.x / bounds.y;
+
+    // We want to scale our dims so that they fit in the bounds, while still being as large as
+    // we can get.
+    let (x, y): (f64, f64);
+
+    // We'll check the ratio to tell which axis has to change.
+    // One of three things will happen:
+    use std::cmp::Ordering;
+    match ratio
+        .partial_cmp(&bounds_ratio)
+        .expect("Expected comparabile dimensions - no NaNs!")
+    {
+        Ordering::Equal => {
+            // 1. The ratio happens to match the bounds' ratio, so we'll use it directly.
+            x = bounds.x;
+            y = bounds.y;
+        }
+        Ordering::Greater => {
+            // 2. We're relatively wider than the bounds, so use the bounds' width and scale our height
+            x = bounds.x;
+            y = bounds.x / ratio;
+        }
+        Ordering::Less => {
+            // 3. We're relatively taller than the bounds, so use the bounds' height and scale our width
+            x = bounds.y * ratio;
+            y = bounds.y;
+        }
+    }
+
+    // Sanity check because this logic took forever to get right.
+    assert!(x <= bounds.x);
+    assert!(y <= bounds.y);
+
+    // Round our chosen dimensions into integer coordinates and we're done!
+    UVec2::new(x.round() as u32, y.round() as u32)
+}
+
+/// Make a square frame centered at `p` with radius `r`
+pub fn make_square_frame(p: DVec2, r: f64) -> (DVec2, DVec2) {
+    let min: DVec2 = DVec2::new(p.x - r, p.y - r);
+    let max: DVec2 = DVec2::new(p.x + r, p.y + r);
+
+    (min, max)
+}
+
+/// Makes a frame that shows "The" Mandelbrot fractal how everyone expects it
+pub fn make_default_frame() -> (DVec2, DVec2) {
+    let min: DVec2 = DVec2::new(-2.5, -1.25);
+    let max: DVec2 = DVec2::new(1.0, 1.25);
+
+    (min, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A small view of the default frame, which is symmetric across the real axis
+    fn small_config() -> SimConfig {
+        SimConfig::builder()
+            .fb_dims(64, 48)
+            .build()
+            .expect("Expected a non-empty framebuffer")
+    }
+
+    /// A sim of `config` that has been run until every cell is done
+    fn finished(config: SimConfig) -> Sim {
+        let mut sim = Sim::new(config);
+        sim.run_until_done(u32::MAX);
+        sim
+    }
+
+    /// Step each of `cells` on its own until it's done, without any of `Sim`'s shortcuts
+    fn stepped_alone(cells: &[GridCell], config: &SimConfig) -> Vec<GridCell> {
+        cells
+            .iter()
+            .map(|&cell| {
+                let mut cell = cell;
+                while !cell.is_done() {
+                    cell.step(config);
+                }
+                cell
+            })
+            .collect()
+    }
+
+    #[test]
+    fn mirrored_update_matches_stepping_each_cell() {
+        for samples in [1, 2] {
+            let config = SimConfig {
+                samples_per_axis: samples,
+                ..small_config()
+            };
+            assert!(config.is_mirrored());
+
+            let mut sim = Sim::new(config);
+            let expected = stepped_alone(&sim.grid, &config);
+            sim.run_until_done(u32::MAX);
+            assert!(sim.grid == expected);
+        }
+    }
+
+    #[test]
+    fn mirrored_update_catches_up_cells_stepped_elsewhere() {
+        let config = small_config();
+        let mut sim = Sim::new(config);
+        let expected = stepped_alone(&sim.grid, &config);
+
+        // Only the top rows, so they're ahead of their reflections
+        for _ in 0..10 {
+            sim.update_region((UVec2::zero(), UVec2::new(config.fb_dims.x, 10)));
+        }
+        sim.run_until_done(u32::MAX);
+        assert!(sim.grid == expected);
+    }
+
+    #[test]
+    fn seeded_sims_are_never_mirrored() {
+        let config = small_config();
+        let size = config.fb_dims.x * config.fb_dims.y;
+        let seeds: Vec<_> = (0..size)
+            .map(|idx| config.idx_to_complex(idx) + Complex::new(0.013, 0.007))
+            .collect();
+
+        let mut sim = Sim::with_seeds(config, seeds.clone());
+        let expected = stepped_alone(&sim.grid, &config);
+        sim.run_until_done(u32::MAX);
+        assert!(sim.grid == expected);
+
+        // Perturbation would work out each cell's point from its index, and ignore the seeds
+        let mut sim = Sim::with_seeds(config, seeds);
+        while sim.update_perturbed().active > 0 {}
+        assert!(sim.grid == expected);
+    }
+
+    #[test]
+    fn sdf_is_negative_inside_and_grows_outward() {
+        let config = small_config();
+        let sim = finished(config);
+        let sdf = sim.render_sdf();
+        let at = |re: f64| sdf[config.complex_to_idx(Complex::new(re, 0.)).unwrap() as usize];
+
+        assert_eq!(at(0.), f32::NEG_INFINITY);
+
+        // The cusp of the main cardioid is at 0.25, and the real axis escapes from there on
+        let pixel = ((config.frame_max.x - config.frame_min.x) / config.fb_dims.x as f64) as f32;
+        let near = at(0.3);
+        let mid = at(0.6);
+        let far = at(0.95);
+        assert!(
+            near > 0. && near < 2. * pixel,
+            "{} should be close to 0",
+            near
+        );
+        assert!(near < mid && mid < far, "{} < {} < {}", near, mid, far);
+    }
+
+    #[test]
+    fn supersampled_sdf_has_one_value_per_pixel() {
+        let config = SimConfig {
+            samples_per_axis: 2,
+            ..small_config()
+        };
+        let sim = finished(config);
+        let sdf = sim.render_sdf();
+        assert_eq!(sdf.len(), (config.fb_dims.x * config.fb_dims.y) as usize);
+
+        // Each pixel agrees with the average over its own four cells
+        let grid_width = (config.fb_dims.x * 2) as usize;
+        for (idx, &distance) in sdf.iter().enumerate() {
+            let x = (idx % config.fb_dims.x as usize) * 2;
+            let y = (idx / config.fb_dims.x as usize) * 2;
+            let cells = [0, 1, grid_width, grid_width + 1]
+                .map(|offset| &sim.grid[y * grid_width + x + offset]);
+
+            let estimates: Vec<f64> = cells
+                .iter()
+                .filter(|cell| cell.has_escaped)
+                .filter_map(|cell| cell.distance_estimate())
+                .collect();
+            if cells.iter().all(|cell| !cell.has_escaped) {
+                assert_eq!(distance, f32::NEG_INFINITY, "pixel {}", idx);
+            } else if !estimates.is_empty() {
+                let mean = estimates.iter().sum::<f64>() / estimates.len() as f64;
+                assert_eq!(distance, mean as f32, "pixel {}", idx);
+            }
+        }
+
+        // And the center of the main cardioid is still inside
+        let center = config.complex_to_idx(Complex::new(0., 0.)).unwrap() as usize;
+        assert_eq!(sdf[center], f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn table_palettes_stay_in_range() {
+        let sim = finished(small_config());
+
+        for color in [palette::with_plain_colors, palette::with_smooth_colors] {
+            let (min, max) = sim.palette_range(color);
+            assert!(min.x >= 0. && min.y >= 0. && min.z >= 0., "{:?}", min);
+            assert!(max.x <= 1. && max.y <= 1. && max.z <= 1., "{:?}", max);
+        }
+    }
+
+    #[test]
+    fn local_orbit_density_handles_any_frame() {
+        let config = small_config();
+        let density = Sim::new(config).render_local_orbit_density(2_000);
+        assert_eq!(
+            density.len(),
+            (config.fb_dims.x * config.fb_dims.y) as usize
+        );
+        assert!(density.iter().any(|&count| count > 0));
+
+        // Zero-width and inverted frames sample nothing useful, but mustn't panic
+        let flat = SimConfig {
+            frame_max: DVec2::new(config.frame_min.x, config.frame_max.y),
+            ..config
+        };
+        let inverted = SimConfig {
+            frame_min: config.frame_max,
+            frame_max: config.frame_min,
+            ..config
+        };
+        for config in [flat, inverted] {
+            let density = Sim::new(config).render_local_orbit_density(100);
+            assert_eq!(
+                density.len(),
+                (config.fb_dims.x * config.fb_dims.y) as usize
+            );
+        }
+    }
+
+    #[test]
+    fn extend_iteration_matches_a_fresh_run() {
+        let low = SimConfig {
+            max_iters: 100,
+            ..small_config()
+        };
+        let high = SimConfig {
+            max_iters: 400,
+            ..low
+        };
+
+        let mut sim = finished(low);
+        sim.extend_iteration(high.max_iters);
+        assert_eq!(sim.config().max_iters, high.max_iters);
+        assert!(sim.grid == finished(high).grid);
+    }
+
+    #[test]
+    fn update_region_over_the_whole_frame_matches_update() {
+        let config = small_config();
+        let mut region = Sim::new(config);
+        let mut whole = Sim::new(config);
+
+        for _ in 0..50 {
+            region.update_region((UVec2::zero(), config.fb_dims));
+            whole.update();
+        }
+        assert!(region.grid == whole.grid);
+    }
+
+    #[test]
+    fn gradient_is_flat_far_out_and_steep_at_the_boundary() {
+        let config = small_config();
+        let gradient = finished(config).gradient_magnitude();
+        let corner = config.complex_to_idx(Complex::new(-2.4, 1.2)).unwrap();
+        assert!(
+            gradient[corner as usize] <= 1.,
+            "{}",
+            gradient[corner as usize]
+        );
+
+        let steepest = gradient.iter().copied().fold(0., f32::max);
+        assert!(steepest > 50., "{}", steepest);
+    }
+
+    #[test]
+    fn coloring_bailout_recolors_without_iterating() {
+        let mut sim = finished(small_config());
+        let cells = sim.grid.clone();
+        let draw = |sim: &Sim| {
+            let mut fb = vec![0; sim.fb_len()];
+            sim.draw(&mut fb, &palette::with_smooth_colors);
+            fb
+        };
+        let before = draw(&sim);
+
+        assert!(!sim.set_coloring_bailout(2. * sim.config().step_radius_sqr));
+        assert!(!sim.set_coloring_bailout(1.));
+        assert!(sim.set_coloring_bailout(16.));
+        assert!(sim.grid == cells);
+        assert_ne!(draw(&sim), before);
+
+        assert!(sim.set_coloring_bailout(sim.config().step_radius_sqr));
+        assert_eq!(draw(&sim), before);
+    }
+
+    #[test]
+    fn profiled_active_counts_never_increase() {
+        let mut sim = Sim::new(small_config());
+        let counts = sim.run_until_done_profiled(u32::MAX);
+
+        assert!(!counts.is_empty());
+        assert!(
+            counts.windows(2).all(|pair| pair[1] <= pair[0]),
+            "{:?}",
+            counts
+        );
+        assert_eq!(counts.last(), Some(&0));
+        assert!(sim.is_done());
+    }
+
+    #[test]
+    fn constant_palette_histogram_is_one_spike_per_channel() {
+        let sim = finished(small_config());
+        let hist = sim.color_histogram(|_: &GridCell| DVec3::new(1., 0.5, 0.));
+
+        let len = sim.fb_len() as u32;
+        for (channel, value) in hist.iter().zip([255, 127, 0]) {
+            assert_eq!(channel[value], len);
+            assert_eq!(channel.iter().sum::<u32>(), len);
+        }
+    }
+
+    #[test]
+    fn non_finite_colors_encode_as_magenta() {
+        for bad in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let color = DVec3::new(0.5, bad, 0.5);
+            assert_eq!(encode(color), NON_FINITE_COLOR);
+            assert_eq!(encode_dithered(color, 0.5), NON_FINITE_COLOR);
+            assert_eq!(encode16(color), [0xFFFF, 0, 0xFFFF, 0xFFFF]);
+        }
+        assert_eq!(NON_FINITE_COLOR, 0xFF00FF);
+
+        let sim = finished(small_config());
+        let mut fb = vec![0; sim.fb_len()];
+        sim.draw(&mut fb, &|_: &GridCell| DVec3::broadcast(f64::NAN));
+        assert!(fb.iter().all(|&pixel| pixel == NON_FINITE_COLOR));
+    }
+
+    #[test]
+    fn iters_rle_round_trips() {
+        let sim = finished(small_config());
+        let runs = sim.iters_rle();
+
+        let iters: Vec<u32> = sim.cells().iter().map(|cell| cell.iters).collect();
+        assert_eq!(decode_iters_rle(&runs), iters);
+
+        // Runs are maximal, so neighbors never repeat a count, and the frame compresses
+        assert!(runs.windows(2).all(|pair| pair[0].0 != pair[1].0));
+        assert!(runs.iter().all(|&(_, count)| count > 0));
+        assert!(runs.len() < iters.len() / 2, "{}", runs.len());
+    }
+
+    #[test]
+    fn power_two_steps_exactly_like_z_squared_plus_c() {
+        let config = small_config();
+        assert_eq!(config.power, 2);
+
+        for mut cell in Sim::new(config).grid {
+            let (mut z, mut dz) = (cell.z, cell.dz);
+            while !cell.is_done() && !cell.reached_max {
+                let (c, dc) = (cell.c, cell.dc);
+                cell.step(&config);
+                if cell.reached_max {
+                    break;
+                }
+
+                (z, dz) = (z * z + c, dz * 2. * z + dc);
+                assert_eq!(cell.z, z, "{:?}", c);
+                assert_eq!(cell.dz, dz, "{:?}", c);
+            }
+        }
+    }
+
+    #[test]
+    fn complex_to_idx_inverts_idx_to_complex() {
+        for config in [
+            small_config(),
+            SimConfig::builder()
+                .fb_dims(7, 5)
+                .center(DVec2::new(0.3, -0.2))
+                .zoom(1e-3)
+                .build()
+                .unwrap(),
+        ] {
+            let len = config.fb_dims.x * config.fb_dims.y;
+            for i in 0..len {
+                assert_eq!(config.complex_to_idx(config.idx_to_complex(i)), Some(i));
+            }
+
+            // The whole frame lands on pixels, out to its corners, and nothing past it does
+            let corner = |x: f64, y: f64| config.complex_to_idx(Complex::new(x, y));
+            let (min, max) = (config.frame_min, config.frame_max);
+            assert_eq!(corner(min.x, max.y), Some(0));
+            assert_eq!(corner(max.x, min.y), Some(len - 1));
+            assert_eq!(corner(max.x, max.y), Some(config.fb_dims.x - 1));
+            let past = max + (max - min);
+            assert_eq!(corner(past.x, max.y), None);
+            assert_eq!(corner(max.x, past.y), None);
+        }
+    }
+
+    #[test]
+    fn out_of_range_colors_clamp() {
+        assert_eq!(encode(DVec3::broadcast(2.)), 0xFFFFFF);
+        assert_eq!(encode(DVec3::broadcast(-1.)), 0x000000);
+        assert_eq!(encode(DVec3::new(2., -1., 2.)), 0xFF00FF);
+        assert_eq!(encode16(DVec3::broadcast(2.)), [u16::MAX; 4]);
+
+        let sim = finished(small_config());
+        let mut fb = vec![0; sim.fb_len()];
+        sim.draw(&mut fb, &|_: &GridCell| DVec3::broadcast(2.));
+        assert!(fb.iter().all(|&pixel| pixel == 0xFFFFFF));
+    }
+
+    #[test]
+    fn main_bulbs_hold_only_interior_points() {
+        let inside = [
+            (0., 0.),
+            (-0.5, 0.),
+            (0.25, 0.),
+            (0.2, 0.5),
+            (-1., 0.),
+            (-1.2, 0.1),
+        ];
+        for (re, im) in inside {
+            assert!(in_main_bulbs(Complex::new(re, im)), "{} {}", re, im);
+        }
+
+        // Including the period-3 bulb at the top, which is inside the set, but not these bulbs
+        let outside = [
+            (0.3, 0.),
+            (0.5, 0.5),
+            (-1.3, 0.),
+            (-2., 0.),
+            (0., 1.),
+            (-0.12, 0.74),
+        ];
+        for (re, im) in outside {
+            assert!(!in_main_bulbs(Complex::new(re, im)), "{} {}", re, im);
+        }
+
+        // Nothing that the bulbs claim escapes, across a grid of points
+        for y in 0..=40 {
+            for x in 0..=60 {
+                let c = Complex::new(-2. + 0.05 * x as f64, -1. + 0.05 * y as f64);
+                if in_main_bulbs(c) {
+                    let orbit = compute_orbit(c, 2_000);
+                    assert!(orbit.iter().all(|z| z.norm_sqr() <= 4.), "{}", c);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reset_matches_a_fresh_sim() {
+        let julia = SimConfig {
+            kind: FractalKind::Julia {
+                c: Complex::new(-0.8, 0.156),
+            },
+            ..small_config()
+        };
+        let supersampled = SimConfig {
+            samples_per_axis: 2,
+            ..small_config()
+        };
+
+        for config in [small_config(), julia, supersampled] {
+            let mut sim = finished(config);
+            sim.reset();
+            assert!(sim.grid == Sim::new(config).grid);
+
+            sim.run_until_done(u32::MAX);
+            assert!(sim.grid == finished(config).grid);
+        }
+    }
+
+    #[test]
+    fn escaped_cells_stop_at_the_step_radius() {
+        // The orbit of 1 is 1, 2, 5, 26, 677, 458330: it escapes on the 3rd iteration, and first
+        // passes the default step radius of 1000 on the 6th
+        let config = small_config();
+        assert_eq!(config.step_radius_sqr, DEFAULT_STEP_RADIUS_SQR);
+
+        let mut sim = Sim::with_seeds(config, vec![Complex::new(1., 0.)]);
+        let mut escaped_at = None;
+        while !sim.is_done() {
+            sim.update();
+            let cell = sim.cells()[0];
+            if cell.has_escaped && escaped_at.is_none() {
+                escaped_at = Some(cell.iters);
+            }
+        }
+
+        let cell = sim.cells()[0];
+        assert_eq!(escaped_at, Some(3));
+        assert_eq!(cell.iters, 6);
+        assert_eq!(cell.z, Complex::new(458_330., 0.));
+        assert!(cell.reached_bailout && !cell.reached_max);
+    }
 }
@@ -0,0 +1,205 @@
+//! Rendering by Mariani-Silver rectangle subdivision
+//!
+//! The Mandelbrot set is connected, so a rectangle whose whole border has the same iteration count
+//! can't hide any detail: every cell inside it has that count too. Only the borders of rectangles
+//! need iterating, and rectangles with uniform borders are filled in without touching their insides.
+//! Rectangles that aren't uniform are split in half along their longer side, and the line they're
+//! split along becomes part of both halves' borders.
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::{GridCell, Sim, SimConfig, SimFloat};
+
+/// Rows of the grid in each band that's subdivided on its own
+///
+/// Bands are independent, so they're what runs in parallel. Their first and last rows are their
+/// top and bottom borders.
+const BAND_ROWS: usize = 64;
+
+/// Rectangles with fewer cells than this across their inside are iterated instead of split
+const MIN_INSIDE: usize = 4;
+
+/// A rectangle of cells, with inclusive bounds, so its edges are its border
+#[derive(Copy, Clone, Debug)]
+struct Rect {
+    x0: usize,
+    y0: usize,
+    x1: usize,
+    y1: usize,
+}
+
+/// What the plain palettes see of a cell: its iteration count if it escaped, and nothing otherwise
+#[inline]
+fn dwell<F: SimFloat>(cell: &GridCell<F>) -> Option<u32> {
+    cell.has_escaped.then_some(cell.iters)
+}
+
+/// Step `cell` until it's done, and return whether it needed any steps
+#[inline]
+fn finish<F: SimFloat>(cell: &mut GridCell<F>, config: &SimConfig) -> bool {
+    if cell.is_done() {
+        return false;
+    }
+
+    while !cell.is_done() {
+        cell.step(config);
+    }
+    true
+}
+
+/// Finish every cell of `rect`'s inside or fill it from its border, in `cells` with rows of `width`
+///
+/// Returns how many cells were iterated.
+fn subdivide<F: SimFloat>(
+    cells: &mut [GridCell<F>],
+    width: usize,
+    rect: Rect,
+    config: &SimConfig,
+) -> u32 {
+    let Rect { x0, y0, x1, y1 } = rect;
+    let mut iterated = 0;
+
+    // Cells on lines shared with a neighbor are already done, so they're free
+    let border = (x0..=x1)
+        .flat_map(|x| [(x, y0), (x, y1)])
+        .chain((y0 + 1..y1).flat_map(|y| [(x0, y), (x1, y)]));
+    let mut dwells = border.map(|(x, y)| {
+        let cell = &mut cells[y * width + x];
+        iterated += finish(cell, config) as u32;
+        dwell(cell)
+    });
+
+    let first = dwells.next().flatten();
+    let uniform = dwells.fold(true, |uniform, d| uniform & (d == first));
+
+    let inside_x = x0 + 1..x1;
+    let inside_y = y0 + 1..y1;
+
+    if uniform {
+        let fill = cells[y0 * width + x0];
+        for y in inside_y {
+            for cell in &mut cells[y * width + inside_x.start..y * width + inside_x.end] {
+                if !cell.is_done() {
+                    let GridCell { c, dc, .. } = *cell;
+                    *cell = GridCell { c, dc, ..fill };
+                }
+            }
+        }
+    } else if inside_x.len() < MIN_INSIDE || inside_y.len() < MIN_INSIDE {
+        for y in inside_y {
+            for cell in &mut cells[y * width + inside_x.start..y * width + inside_x.end] {
+                iterated += finish(cell, config) as u32;
+            }
+        }
+    } else if x1 - x0 >= y1 - y0 {
+        let mid = (x0 + x1) / 2;
+        iterated += subdivide(cells, width, Rect { x1: mid, ..rect }, config);
+        iterated += subdivide(cells, width, Rect { x0: mid, ..rect }, config);
+    } else {
+        let mid = (y0 + y1) / 2;
+        iterated += subdivide(cells, width, Rect { y1: mid, ..rect }, config);
+        iterated += subdivide(cells, width, Rect { y0: mid, ..rect }, config);
+    }
+
+    iterated
+}
+
+/// Subdivide a whole band of rows of the grid, with rows of `width`
+fn subdivide_band<F: SimFloat>(band: &mut [GridCell<F>], width: usize, config: &SimConfig) -> u32 {
+    let rect = Rect {
+        x0: 0,
+        y0: 0,
+        x1: width - 1,
+        y1: band.len() / width - 1,
+    };
+
+    subdivide(band, width, rect, config)
+}
+
+impl<F: SimFloat> Sim<F> {
+    /// Run every cell until it's done, by Mariani-Silver subdivision
+    ///
+    /// Instead of iterating every cell, this only iterates the borders of rectangles of the grid.
+    /// When a rectangle's border is all the same (escaped with the same `iters`, or not escaped at
+    /// all), its inside is filled with copies of a border cell that keep their own `c`. Otherwise
+    /// it's split in half, and each half is handled the same way. On the default frame this iterates
+    /// only a fraction of the cells. Returns how many cells were iterated.
+    ///
+    /// Filled cells only get the right `has_escaped`, and `iters` if they escaped. Everything else,
+    /// like `z`, `dz`, and `smooth_iters`, is the border cell's, so only draw the result with
+    /// palettes that read nothing else, like `palette::with_plain_colors`. Smooth, lighting, and
+    /// distance palettes will show flat rectangles.
+    ///
+    /// This relies on the set being connected, so it suits the Mandelbrot set. Julia sets that
+    /// aren't connected can lose islands that fall entirely inside of a uniform rectangle.
+    pub fn update_mariani_silver(&mut self) -> u32 {
+        let config = self.config;
+        let width = config.grid_dims().x as usize;
+        if self.grid.is_empty() {
+            return 0;
+        }
+        self.mirror_in_step = false;
+        self.mark_all_dirty();
+
+        #[cfg(feature = "rayon")]
+        {
+            self.grid
+                .par_chunks_mut(width * BAND_ROWS)
+                .map(|band| subdivide_band(band, width, &config))
+                .sum()
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            self.grid
+                .chunks_mut(width * BAND_ROWS)
+                .map(|band| subdivide_band(band, width, &config))
+                .sum()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subdivision_matches_a_full_render_with_fewer_cells() {
+        let config = SimConfig::builder()
+            .fb_dims(320, 240)
+            .build()
+            .expect("Expected a non-empty framebuffer");
+
+        let mut full = Sim::new(config);
+        full.run_until_done(u32::MAX);
+
+        let mut subdivided = Sim::new(config);
+        let iterated = subdivided.update_mariani_silver();
+
+        // Every cell escapes with the same `iters`, or doesn't escape. Cells inside the set stop
+        // wherever their own periodicity check caught them, so filled ones can have other `iters`.
+        let differing = full
+            .cells()
+            .iter()
+            .zip(subdivided.cells())
+            .filter(|(a, b)| dwell(a) != dwell(b))
+            .count();
+        assert_eq!(differing, 0);
+
+        // So the plain palette can't tell them apart
+        let draw = |sim: &Sim| {
+            let mut fb = vec![0; (config.fb_dims.x * config.fb_dims.y) as usize];
+            sim.draw(&mut fb, &crate::palette::with_plain_colors);
+            fb
+        };
+        assert!(draw(&full) == draw(&subdivided));
+
+        assert!(
+            (iterated as usize) < full.cells().len(),
+            "Iterated {} of {} cells",
+            iterated,
+            full.cells().len()
+        );
+    }
+}
@@ -0,0 +1,54 @@
+//! Conversions between `num::Complex` and `ultraviolet` vectors
+//!
+//! Both crates are foreign, so we can't implement `From` between them. These extension traits are the
+//! next best thing, and keep the real part on `x` and the imaginary part on `y` everywhere.
+
+use num::Complex;
+use ultraviolet::DVec2;
+
+/// Convert into a complex number, with `x` as the real part and `y` as the imaginary part
+pub trait ToComplex {
+    fn to_complex(self) -> Complex<f64>;
+}
+
+/// Convert into a 2D vector, with the real part as `x` and the imaginary part as `y`
+pub trait ToDVec2 {
+    fn to_dvec2(self) -> DVec2;
+}
+
+impl ToComplex for DVec2 {
+    #[inline]
+    fn to_complex(self) -> Complex<f64> {
+        Complex::new(self.x, self.y)
+    }
+}
+
+impl ToDVec2 for Complex<f64> {
+    #[inline]
+    fn to_dvec2(self) -> DVec2 {
+        DVec2::new(self.re, self.im)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions_round_trip() {
+        let points = [
+            (0., 0.),
+            (1.5, -2.25),
+            (-0.75, 0.1),
+            (f64::MAX, f64::MIN_POSITIVE),
+        ];
+
+        for (x, y) in points {
+            let v = DVec2::new(x, y);
+            let c = v.to_complex();
+            assert_eq!((c.re, c.im), (x, y));
+            assert_eq!(c.to_dvec2(), v);
+            assert_eq!(c.to_dvec2().to_complex(), c);
+        }
+    }
+}
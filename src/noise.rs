@@ -0,0 +1,93 @@
+//! Noise to break up banding when quantizing colors to 8 bits
+
+use ultraviolet::DVec3;
+
+use crate::{DrawOptions, GridCell, Sim};
+
+/// Thresholds for ordered dithering, as a 4x4 Bayer matrix
+const BAYER_4X4: [u8; 16] = [
+    0, 8, 2, 10, //
+    12, 4, 14, 6, //
+    3, 11, 1, 9, //
+    15, 7, 13, 5, //
+];
+
+/// Side length of the tile in `BLUE_NOISE`
+const BLUE_NOISE_DIM: usize = 16;
+
+/// A tileable 16x16 blue noise texture, with every value in `0..256` appearing exactly once
+///
+/// This was generated with the void-and-cluster method, using a Gaussian of standard deviation 1.9
+/// that wraps around the edges of the tile.
+#[rustfmt::skip]
+const BLUE_NOISE: [u8; BLUE_NOISE_DIM * BLUE_NOISE_DIM] = [
+    203, 231, 121, 145, 174, 62, 136, 187, 157, 21, 130, 75, 12, 99, 17, 83,
+    160, 22, 1, 217, 87, 229, 11, 79, 50, 219, 240, 167, 204, 142, 53, 178,
+    93, 242, 68, 189, 44, 117, 165, 236, 101, 195, 30, 118, 45, 188, 253, 115,
+    42, 129, 169, 106, 247, 150, 19, 207, 125, 147, 63, 89, 214, 4, 70, 220,
+    151, 208, 80, 32, 197, 57, 73, 180, 40, 8, 176, 246, 154, 105, 138, 26,
+    61, 237, 13, 141, 221, 96, 133, 250, 109, 82, 225, 131, 35, 199, 233, 171,
+    112, 193, 51, 122, 162, 6, 230, 25, 213, 166, 192, 20, 55, 76, 92, 18,
+    222, 85, 175, 254, 39, 185, 90, 153, 48, 67, 98, 119, 161, 249, 183, 127,
+    158, 2, 102, 69, 205, 114, 58, 202, 139, 0, 241, 206, 144, 10, 211, 46,
+    245, 143, 232, 27, 148, 78, 239, 172, 124, 228, 86, 41, 177, 31, 104, 65,
+    186, 36, 198, 128, 215, 9, 23, 100, 33, 182, 156, 59, 113, 224, 134, 81,
+    15, 116, 60, 91, 164, 248, 135, 194, 74, 218, 14, 255, 72, 196, 235, 163,
+    209, 170, 226, 43, 107, 181, 54, 234, 47, 120, 103, 140, 173, 5, 49, 94,
+    251, 137, 7, 191, 71, 16, 152, 84, 168, 200, 28, 210, 88, 123, 149, 24,
+    108, 77, 155, 243, 212, 126, 111, 223, 3, 146, 244, 56, 38, 190, 216, 64,
+    34, 184, 52, 97, 29, 201, 37, 252, 95, 66, 179, 110, 227, 159, 238, 132,
+];
+
+/// How to break up banding when a color is quantized to 8 bits
+///
+/// Smooth palettes can still show bands on nearly flat gradients, since neighboring pixels all
+/// round to the same value. Both kinds of noise nudge each pixel by up to 1 LSB before it's
+/// quantized, which is imperceptible on its own but replaces bands with a fine grain.
+///
+/// The noise comes from fixed, tiled textures, so drawing the same sim twice still gives the same
+/// pixels. It does trade exact reproducibility with `Sim::draw` for smoother gradients: most pixels
+/// match, but some are off by one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NoiseKind {
+    /// Quantize exactly like `Sim::draw`
+    #[default]
+    None,
+
+    /// A 4x4 Bayer matrix, which is cheap but leaves a visible cross-hatch pattern
+    Ordered,
+
+    /// A tiled blue noise texture, which has no low frequencies, so it looks like an even grain
+    BlueNoise,
+}
+
+impl NoiseKind {
+    /// Threshold to add to the pixel at `(x, y)` before truncating, in `[0, 1)`
+    #[inline]
+    pub(crate) fn threshold(self, x: u32, y: u32) -> f64 {
+        let (x, y) = (x as usize, y as usize);
+
+        match self {
+            NoiseKind::None => 0.,
+            NoiseKind::Ordered => (BAYER_4X4[(y % 4) * 4 + x % 4] as f64 + 0.5) / 16.,
+            NoiseKind::BlueNoise => {
+                let i = (y % BLUE_NOISE_DIM) * BLUE_NOISE_DIM + x % BLUE_NOISE_DIM;
+                (BLUE_NOISE[i] as f64 + 0.5) / 256.
+            }
+        }
+    }
+}
+
+impl Sim {
+    /// Like `draw`, but add `noise` to each pixel before it's quantized
+    pub fn draw_with_noise<ColorFn>(&self, fb: &mut [u32], color: ColorFn, noise: NoiseKind)
+    where
+        ColorFn: Fn(&GridCell) -> DVec3 + Sync,
+    {
+        let options = DrawOptions {
+            noise,
+            ..DrawOptions::default()
+        };
+        self.draw_with_options(fb, color, &options);
+    }
+}
@@ -1,14 +1,51 @@
 use std::f64::consts::TAU;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
 
 use num::Complex;
-use ultraviolet::{DVec2, DVec3};
+use ultraviolet::DVec3;
 
+use crate::math::ToDVec2;
 use crate::GridCell;
-use crate::R2;
+use crate::Sim;
+use crate::DEFAULT_MAX_ITERS;
+use crate::DEFAULT_STEP_RADIUS_SQR;
 
-// Use a color palette that cycles based off of iterations
+/// Width of a pixel, in complex-plane units, that `with_distance_estimate` assumes
+///
+/// This is roughly a pixel of the default frame at 1080 pixels wide.
+const DISTANCE_ESTIMATE_PIXEL: f64 = 3.5 / 1080.;
+
+/// Where the light is for the lambert palettes, above the complex plane at `z = 0`
+///
+/// Pass a different position to `lambert_with_light` and friends to move it.
+pub const DEFAULT_LIGHT: DVec3 = DVec3::new(-2.1, 0.75, 4.);
+
+/// Direction towards the viewer that `specular` uses: looking straight down at the plane
+const VIEW_DIR: DVec3 = DVec3::new(0., 0., 1.);
+
+/// Specular exponent that `with_glossy_lambert` uses
+const LAMBERT_SHININESS: f64 = 32.;
+
+/// The sky that `with_chrome` reflects, from the ground straight down to the zenith
+const CHROME_SKY: [DVec3; 5] = [
+    DVec3::new(0.05, 0.04, 0.03),
+    DVec3::new(0.35, 0.3, 0.25),
+    DVec3::new(1., 0.97, 0.9),
+    DVec3::new(0.45, 0.65, 0.95),
+    DVec3::new(0.1, 0.2, 0.55),
+];
+
+/// Number of iterations `with_external_angle` follows a point for before giving up
+const EXTERNAL_ANGLE_ITERS: u32 = 1_000;
+
+/// The colors that `with_plain_colors` and friends cycle through, with 8-bit channels in `[0, 255]`
+///
+/// Pass your own table to `plain_colors` or `smooth_colors` to render with a different ramp.
 // Sourced from StackOverflow: https://stackoverflow.com/a/16505538
-const COLOR_MAPPING: [DVec3; 16] = [
+pub const DEFAULT_COLORS: [DVec3; 16] = [
     DVec3::new(66., 30., 15.),
     DVec3::new(25., 7., 26.),
     DVec3::new(9., 1., 47.),
@@ -27,23 +64,446 @@ const COLOR_MAPPING: [DVec3; 16] = [
     DVec3::new(106., 52., 3.),
 ];
 
+/// Anything that can color a cell, to draw with
+///
+/// Every `Fn(&GridCell) -> DVec3` is a palette, so the functions in this module all are. Implement
+/// this for a type instead to carry state along, like a precomputed LUT or a moving light.
+pub trait Palette: Sync {
+    fn color(&self, cell: &GridCell) -> DVec3;
+}
+
+impl<F> Palette for F
+where
+    F: Fn(&GridCell) -> DVec3 + Sync,
+{
+    #[inline]
+    fn color(&self, cell: &GridCell) -> DVec3 {
+        self(cell)
+    }
+}
+
+/// A plain palette function, like the ones in this module
+pub type PaletteFn = fn(&GridCell) -> DVec3;
+
+/// A palette function along with a short, human-readable name for it
+#[derive(Copy, Clone, Debug)]
+pub struct PaletteInfo {
+    pub name: &'static str,
+    pub color: PaletteFn,
+}
+
+/// Every palette in this module, in the order the viewer cycles through them
+pub const ALL: [PaletteInfo; 12] = [
+    PaletteInfo {
+        name: "plain",
+        color: with_plain_colors,
+    },
+    PaletteInfo {
+        name: "smooth_stripes",
+        color: with_smooth_stripes,
+    },
+    PaletteInfo {
+        name: "lambert",
+        color: with_lambert_and_colors,
+    },
+    PaletteInfo {
+        name: "white_lambert",
+        color: with_white_lambert,
+    },
+    PaletteInfo {
+        name: "dz",
+        color: with_color_from_dz,
+    },
+    PaletteInfo {
+        name: "external_angle",
+        color: with_external_angle,
+    },
+    PaletteInfo {
+        name: "smooth",
+        color: with_smooth_colors,
+    },
+    PaletteInfo {
+        name: "distance_estimate",
+        color: with_distance_estimate,
+    },
+    PaletteInfo {
+        name: "grayscale",
+        color: with_grayscale,
+    },
+    PaletteInfo {
+        name: "glossy_lambert",
+        color: with_glossy_lambert,
+    },
+    PaletteInfo {
+        name: "two_lights",
+        color: with_two_lights,
+    },
+    PaletteInfo {
+        name: "chrome",
+        color: with_chrome,
+    },
+];
+
+/// The name of every palette in `ALL`, in the same order
+const NAMES: [&str; ALL.len()] = {
+    let mut names = [""; ALL.len()];
+    let mut i = 0;
+    while i < ALL.len() {
+        names[i] = ALL[i].name;
+        i += 1;
+    }
+    names
+};
+
+/// Look up a palette in `ALL` by its name, like `"smooth"`
+///
+/// Returns `None` for names that aren't in `ALL`, see `names` for the ones that are.
+pub fn by_name(name: &str) -> Option<PaletteFn> {
+    ALL.iter()
+        .find(|info| info.name == name)
+        .map(|info| info.color)
+}
+
+/// The name of every palette in `ALL`, in the order the viewer cycles through them
+pub fn names() -> &'static [&'static str] {
+    &NAMES
+}
+
 pub fn with_plain_colors(cell: &GridCell) -> DVec3 {
+    plain_colors(cell, &DEFAULT_COLORS)
+}
+
+/// Like `with_plain_colors`, but cycle through `colors` instead of `DEFAULT_COLORS`
+///
+/// `colors` has 8-bit channels in `[0, 255]`, like `DEFAULT_COLORS`, and must not be empty.
+pub fn plain_colors(cell: &GridCell, colors: &[DVec3]) -> DVec3 {
     if cell.has_escaped {
-        // Color from iterations
-        COLOR_MAPPING[cell.iters as usize % COLOR_MAPPING.len()] / 255.
+        colors[cell.iters as usize % colors.len()] / 255.
+    } else {
+        DVec3::broadcast(0.)
+    }
+}
+
+/// Like `with_plain_colors`, but blend between neighboring colors with `smooth_iters`, so it doesn't
+/// band
+pub fn with_smooth_colors(cell: &GridCell) -> DVec3 {
+    smooth_colors(cell, &DEFAULT_COLORS)
+}
+
+/// Like `with_smooth_colors`, but blend through `colors` instead of `DEFAULT_COLORS`
+///
+/// `colors` has 8-bit channels in `[0, 255]`, like `DEFAULT_COLORS`, and must not be empty.
+pub fn smooth_colors(cell: &GridCell, colors: &[DVec3]) -> DVec3 {
+    if cell.has_escaped {
+        let t = cell.smooth_iters.rem_euclid(colors.len() as f64);
+        let i = t.floor() as usize;
+        let s = t - t.floor();
+
+        let c0 = colors[i % colors.len()];
+        let c1 = colors[(i + 1) % colors.len()];
+        ((1. - s) * c0 + s * c1) / 255.
     } else {
         DVec3::broadcast(0.)
     }
 }
 
+/// Like `with_smooth_colors`, but sample `gradient` instead of a table, going once through it every
+/// `period` iterations
+///
+/// Unlike the fixed tables, a gradient can have any number of stops, like one from
+/// `Gradient::from_csv`.
+pub fn cycle_gradient(cell: &GridCell, gradient: &Gradient, period: f64) -> DVec3 {
+    if cell.has_escaped {
+        gradient.sample((cell.smooth_iters / period).rem_euclid(1.))
+    } else {
+        DVec3::broadcast(0.)
+    }
+}
+
+/// Shade escaped cells from black to white by their smooth iteration count, over
+/// `DEFAULT_MAX_ITERS` iterations
+///
+/// See `grayscale` to normalize by a sim's own `max_iters`.
+pub fn with_grayscale(cell: &GridCell) -> DVec3 {
+    grayscale(cell, DEFAULT_MAX_ITERS)
+}
+
+/// Like `with_grayscale`, but brightness is `smooth_iters / max_iters`
+///
+/// Cells that haven't escaped are black. The brightness is clamped to `[0, 1]`, so no palette table
+/// is involved, and the output is easy to post-process.
+pub fn grayscale(cell: &GridCell, max_iters: u32) -> DVec3 {
+    if !cell.has_escaped {
+        return DVec3::broadcast(0.);
+    }
+
+    let v = cell.smooth_iters / max_iters.max(1) as f64;
+    DVec3::broadcast(v.clamp(0., 1.))
+}
+
+/// A tiny, seeded random number generator, so random colors are the same on every run
+///
+/// This is SplitMix64, which is plenty for picking colors, and doesn't need a dependency.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A random 8-bit channel value, in `[0, 255]`
+    fn next_channel(&mut self) -> f64 {
+        (self.next_u64() >> 56) as f64
+    }
+}
+
+/// A table of random colors, for `plain_colors` or `smooth_colors`, picked from `seed`
+///
+/// The same seed always gives the same colors, on every platform, so a render with random colors
+/// can be reproduced by its seed alone.
+pub fn random_colors(seed: u64) -> [DVec3; 16] {
+    let mut rng = SplitMix64(seed);
+    std::array::from_fn(|_| DVec3::new(rng.next_channel(), rng.next_channel(), rng.next_channel()))
+}
+
+/// The color `with_plain_colors` gives a cell that escaped after `iters` iterations
+///
+/// This only depends on `iters`, so it can build a LUT with `iter_to_color_lut`.
+pub fn plain_color(iters: u32) -> DVec3 {
+    DEFAULT_COLORS[iters as usize % DEFAULT_COLORS.len()] / 255.
+}
+
+/// A color ramp, sampled by position in `[0, 1]`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    /// `(position, color)` pairs, sorted by position
+    stops: Vec<(f64, DVec3)>,
+}
+
+impl Gradient {
+    /// Make a gradient with `colors` spaced evenly from `0` to `1`
+    pub fn new(colors: Vec<DVec3>) -> Self {
+        let last = colors.len().saturating_sub(1).max(1) as f64;
+        let stops = colors
+            .into_iter()
+            .enumerate()
+            .map(|(i, color)| (i as f64 / last, color))
+            .collect();
+
+        Self { stops }
+    }
+
+    /// Make a gradient from `(position, color)` pairs
+    ///
+    /// Positions don't need to be sorted, or to span all of `[0, 1]`. Before the first stop and after
+    /// the last, the gradient holds the nearest stop's color.
+    pub fn with_positions(mut stops: Vec<(f64, DVec3)>) -> Self {
+        stops.sort_by(|a, b| {
+            a.0.partial_cmp(&b.0)
+                .expect("Expected positions - no NaNs!")
+        });
+
+        Self { stops }
+    }
+
+    /// Read a gradient from a file with one color per line, spaced evenly like `new`
+    ///
+    /// Each line is either `r,g,b` with channels in `[0, 255]`, or a hex color like `#ff8800`. Blank
+    /// lines are skipped. Files with no colors, or with a line that isn't a color, are an
+    /// `InvalidData` error naming the offending line.
+    pub fn from_csv(path: impl AsRef<Path>) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        let mut colors = vec![];
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let color = parse_color(line).ok_or_else(|| {
+                invalid(format!(
+                    "line {}: expected `r,g,b` or `#rrggbb`, found {:?}",
+                    i + 1,
+                    line
+                ))
+            })?;
+            colors.push(color / 255.);
+        }
+
+        if colors.is_empty() {
+            return Err(invalid("expected at least one color".to_string()));
+        }
+
+        Ok(Self::new(colors))
+    }
+
+    /// The `(position, color)` pairs in this gradient, sorted by position
+    pub fn stops(&self) -> &[(f64, DVec3)] {
+        &self.stops
+    }
+
+    /// Linearly interpolate the color at `t`
+    ///
+    /// An empty gradient is black everywhere.
+    pub fn sample(&self, t: f64) -> DVec3 {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return DVec3::broadcast(0.),
+        };
+
+        if t <= first.0 || t.is_nan() {
+            return first.1;
+        }
+        if t >= last.0 {
+            return last.1;
+        }
+
+        // Find the pair of stops around `t`
+        let i = self.stops.partition_point(|&(pos, _)| pos <= t);
+        let (p0, c0) = self.stops[i - 1];
+        let (p1, c1) = self.stops[i];
+
+        let s = (t - p0) / (p1 - p0);
+        (1. - s) * c0 + s * c1
+    }
+}
+
+/// Colors precomputed by iteration count, so drawing is a single index per cell
+///
+/// A `Lut` is a `Palette` itself: escaped cells get the color for their `iters`, clamped to the last
+/// one, and cells that haven't escaped are black. For the fastest draws, pass `encoded` to
+/// `Sim::draw_with_lut`; `benches/draw.rs` times both against sampling the gradient for every cell.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lut {
+    /// One color for each iteration count in `0..=max_iters`
+    colors: Vec<DVec3>,
+}
+
+impl Lut {
+    /// Sample `gradient` once for every iteration count up to `max_iters`, spread across `[0, 1]`
+    pub fn build(gradient: &Gradient, max_iters: u32) -> Self {
+        let last = max_iters.max(1) as f64;
+        let colors = (0..=max_iters)
+            .map(|iters| gradient.sample(iters as f64 / last))
+            .collect();
+
+        Self { colors }
+    }
+
+    /// The color for each iteration count, starting from `0`
+    pub fn colors(&self) -> &[DVec3] {
+        &self.colors
+    }
+
+    /// This table, encoded as pixels for `Sim::draw_with_lut`
+    pub fn encoded(&self) -> Vec<u32> {
+        let max_iters = self.colors.len() as u32 - 1;
+        crate::iter_to_color_lut(
+            max_iters,
+            |iters| self.colors[iters as usize],
+            DVec3::zero(),
+        )
+    }
+}
+
+impl Palette for Lut {
+    #[inline]
+    fn color(&self, cell: &GridCell) -> DVec3 {
+        if cell.has_escaped {
+            self.colors[(cell.iters as usize).min(self.colors.len() - 1)]
+        } else {
+            DVec3::zero()
+        }
+    }
+}
+
+/// Parse `r,g,b` or `#rrggbb` into a color with channels in `[0, 255]`
+fn parse_color(text: &str) -> Option<DVec3> {
+    let channels: Vec<u8> = match text.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 && hex.is_ascii() => (0..6)
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+            .collect::<Option<_>>()?,
+        Some(_) => return None,
+        None => text
+            .split(',')
+            .map(|channel| channel.trim().parse().ok())
+            .collect::<Option<_>>()?,
+    };
+
+    match channels[..] {
+        [r, g, b] => Some(DVec3::new(r as f64, g as f64, b as f64)),
+        _ => None,
+    }
+}
+
+impl Sim {
+    /// Suggest a gradient that suits the distribution of iteration counts in this frame
+    ///
+    /// The colors of the plain palette are placed at evenly spaced quantiles of the iteration counts
+    /// of escaped cells: the first color at the smallest count, the middle color at the median, and so
+    /// on. Since the stops are evenly spaced in the CDF, the most common counts get distinct,
+    /// well-separated colors, no matter how the frame is zoomed.
+    ///
+    /// Like `Lut::build`, each stop's position is its iteration count divided by `max_iters`, so they
+    /// all fall in `[0, 1]`. Sample it with `gradient.sample(cell.iters as f64 / max_iters as f64)`,
+    /// or pass it to `Lut::build` with this sim's `max_iters`, and color cells that haven't escaped
+    /// separately. If no cells have escaped, the gradient is empty. This is only a starting point, not
+    /// a fixed scheme - feel free to tweak the stops it returns.
+    pub fn suggest_palette(&self) -> Gradient {
+        let mut iters: Vec<u32> = self
+            .grid
+            .iter()
+            .filter(|cell| cell.has_escaped)
+            .map(|cell| cell.iters)
+            .collect();
+        if iters.is_empty() {
+            return Gradient::new(vec![]);
+        }
+        iters.sort_unstable();
+
+        // Escaped cells keep stepping past `max_iters` on their way to the bailout, so clamp them
+        let max_iters = self.config.max_iters.max(1) as f64;
+        let last = (DEFAULT_COLORS.len() - 1) as f64;
+        let stops = DEFAULT_COLORS
+            .iter()
+            .enumerate()
+            .map(|(i, &color)| {
+                let q = i as f64 / last;
+                let idx = (q * (iters.len() - 1) as f64).round() as usize;
+                ((iters[idx] as f64 / max_iters).min(1.), color / 255.)
+            })
+            .collect();
+
+        Gradient::with_positions(stops)
+    }
+}
+
+/// Stripes that follow the smooth iteration count, for the default `SimConfig::step_radius_sqr`
+///
+/// Sims with a different `step_radius_sqr` can use
+/// `smooth_stripes(cell, config.step_radius_sqr)` instead.
 pub fn with_smooth_stripes(cell: &GridCell) -> DVec3 {
+    smooth_stripes(cell, DEFAULT_STEP_RADIUS_SQR)
+}
+
+/// Like `with_smooth_stripes`, but treat cells as escaped once `|z|^2 > bailout`
+///
+/// See `Sim::coloring_bailout`.
+pub fn smooth_stripes(cell: &GridCell, bailout: f64) -> DVec3 {
     fn f(x: f64) -> DVec3 {
         let c = (1. + f64::cos(TAU * x)) / 2.;
         DVec3::broadcast(c)
     }
 
     let z2 = cell.z.norm_sqr();
-    if z2 > R2 as f64 {
+    if z2 > bailout {
         let v: f64 = f64::log2(z2) / f64::powf(2., cell.iters as f64);
         f(v.log2())
     } else {
@@ -51,32 +511,103 @@ pub fn with_smooth_stripes(cell: &GridCell) -> DVec3 {
     }
 }
 
-pub fn with_lambert_and_colors(cell: &GridCell) -> DVec3 {
-    let color = if cell.has_escaped {
-        // Color from iterations
-        COLOR_MAPPING[cell.iters as usize % COLOR_MAPPING.len()] / 255.
-    } else {
-        0.8 * DVec3::new(205., 92., 92.) / 255.
-    };
-
-    // Normal of the "surface"
+/// Normal of the "surface" that the lighting palettes shade
+///
+/// This points in the direction of `z / dz` in the plane, tilted up 45 degrees towards `+z`. It's not
+/// normalized: the in-plane part has length 1 and `z` is always 1.
+pub fn surface_normal(cell: &GridCell) -> DVec3 {
     let u: Complex<_> = cell.z / cell.dz;
-    let u = DVec2::new(u.re, u.im).normalized();
-    let n = DVec3::new(u.x, u.y, 1.);
+    let u = u.to_dvec2().normalized();
 
+    DVec3::new(u.x, u.y, 1.)
+}
+
+/// Direction from the "surface" to the light, for a light at `light`
+fn light_dir(cell: &GridCell, light: DVec3) -> DVec3 {
     // Our point's location
     let pos = DVec3::new(cell.c.re, cell.c.im, 0.);
 
-    // Light source
-    const L_POS: DVec3 = DVec3::new(-2.1, 0.75, 4.);
-    let l_dir = (L_POS - pos).normalized();
+    (light - pos).normalized()
+}
+
+/// How brightly a light at `light` diffusely lights the "surface" at `cell`
+fn diffuse(cell: &GridCell, light: DVec3) -> f64 {
+    // Normal of the "surface"
+    let n = surface_normal(cell);
+
+    let l_dir = light_dir(cell, light);
     let t = 0.75 / l_dir.mag();
     let t = t.max(0.0);
 
-    t * n.dot(l_dir).max(0.0) * color
+    t * n.dot(l_dir).max(0.0)
+}
+
+/// Blinn-Phong highlight from a light at `light` on the "surface" at `cell`, in `[0, 1]`
+///
+/// The surface is viewed from straight above, along `+z`. Larger `shininess` exponents give
+/// tighter, glossier highlights.
+pub fn specular(cell: &GridCell, light: DVec3, shininess: f64) -> f64 {
+    let n = surface_normal(cell).normalized();
+    let half = (light_dir(cell, light) + VIEW_DIR).normalized();
+
+    n.dot(half).max(0.).powf(shininess)
+}
+
+/// The colors that `with_lambert_and_colors` shades
+fn lambert_color(cell: &GridCell) -> DVec3 {
+    if cell.has_escaped {
+        // Color from iterations
+        DEFAULT_COLORS[cell.iters as usize % DEFAULT_COLORS.len()] / 255.
+    } else {
+        0.8 * DVec3::new(205., 92., 92.) / 255.
+    }
+}
+
+pub fn with_lambert_and_colors(cell: &GridCell) -> DVec3 {
+    lambert_with_light(cell, DEFAULT_LIGHT)
+}
+
+/// Like `with_lambert_and_colors`, but lit by a light at `light` instead of `DEFAULT_LIGHT`
+///
+/// Moving `light` from frame to frame animates the lighting.
+pub fn lambert_with_light(cell: &GridCell, light: DVec3) -> DVec3 {
+    diffuse(cell, light) * lambert_color(cell)
+}
+
+/// Like `lambert_with_light`, but lit by several `(position, color)` lights at once
+///
+/// Each light's diffuse contribution is tinted by its color and summed, and the total is clamped
+/// to `[0, 1]` so overlapping lights don't blow out. A single white light at `DEFAULT_LIGHT` gives
+/// `with_lambert_and_colors`.
+pub fn lambert_multi(cell: &GridCell, lights: &[(DVec3, DVec3)]) -> DVec3 {
+    let total = lights
+        .iter()
+        .fold(DVec3::zero(), |sum, &(light, light_color)| {
+            sum + diffuse(cell, light) * light_color
+        });
+
+    (total * lambert_color(cell)).clamped(DVec3::broadcast(0.), DVec3::broadcast(1.))
+}
+
+/// `lambert_multi` with a warm key light at `DEFAULT_LIGHT` and a cool fill light across from it
+pub fn with_two_lights(cell: &GridCell) -> DVec3 {
+    const FILL_LIGHT: DVec3 = DVec3::new(1.5, -1.5, 3.);
+
+    lambert_multi(
+        cell,
+        &[
+            (DEFAULT_LIGHT, DVec3::new(1., 0.85, 0.6)),
+            (FILL_LIGHT, DVec3::new(0.3, 0.5, 1.)),
+        ],
+    )
 }
 
 pub fn with_white_lambert(cell: &GridCell) -> DVec3 {
+    white_lambert_with_light(cell, DEFAULT_LIGHT)
+}
+
+/// Like `with_white_lambert`, but lit by a light at `light` instead of `DEFAULT_LIGHT`
+pub fn white_lambert_with_light(cell: &GridCell, light: DVec3) -> DVec3 {
     let color = if cell.has_escaped {
         DVec3::new(1., 1., 1.)
     } else {
@@ -84,21 +615,24 @@ pub fn with_white_lambert(cell: &GridCell) -> DVec3 {
         DVec3::new(0., 0., 0.)
     };
 
-    // Normal of the "surface"
-    let u: Complex<_> = cell.z / cell.dz;
-    let u = DVec2::new(u.re, u.im).normalized();
-    let n = DVec3::new(u.x, u.y, 1.);
+    diffuse(cell, light) * color
+}
 
-    // Our point's location
-    let pos = DVec3::new(cell.c.re, cell.c.im, 0.);
+/// Like `with_lambert_and_colors`, with glossy highlights of `LAMBERT_SHININESS`
+pub fn with_glossy_lambert(cell: &GridCell) -> DVec3 {
+    glossy_lambert(cell, LAMBERT_SHININESS)
+}
 
-    // Light source
-    const L_POS: DVec3 = DVec3::new(-2.1, 0.75, 4.);
-    let l_dir = (L_POS - pos).normalized();
-    let t = 0.75 / l_dir.mag();
-    let t = t.max(0.0);
+/// Like `with_lambert_and_colors`, plus a white Blinn-Phong highlight on escaped cells
+///
+/// See `specular` for `shininess`.
+pub fn glossy_lambert(cell: &GridCell, shininess: f64) -> DVec3 {
+    let color = with_lambert_and_colors(cell);
+    if !cell.has_escaped {
+        return color;
+    }
 
-    t * n.dot(l_dir).max(0.0) * color
+    color + DVec3::broadcast(specular(cell, DEFAULT_LIGHT, shininess))
 }
 
 pub fn with_color_from_dz(cell: &GridCell) -> DVec3 {
@@ -107,5 +641,132 @@ pub fn with_color_from_dz(cell: &GridCell) -> DVec3 {
     // Color from the derivative of z
     // This does not distinguish between escaped or not, but dz relates to this anyway, so
     // it's still visible in the final image.
-    COLOR_MAPPING[x as usize % COLOR_MAPPING.len()] / 255.
+    DEFAULT_COLORS[x as usize % DEFAULT_COLORS.len()] / 255.
+}
+
+/// Draw the boundary of the set as crisp white edges, from the exterior distance estimate
+///
+/// Uses a pixel width suited to the default frame. See `distance_estimate_shade` to pick your own.
+pub fn with_distance_estimate(cell: &GridCell) -> DVec3 {
+    distance_estimate_shade(cell, DISTANCE_ESTIMATE_PIXEL)
+}
+
+/// Shade by `GridCell::distance_estimate`, relative to the width of a pixel in complex-plane units
+///
+/// Cells right on the boundary are white, fading to black by one `pixel_size` away. Since the
+/// distance is exact rather than sampled, the edges stay sharp at any resolution, as long as
+/// `pixel_size` matches it. Cells without a usable estimate, like cells inside the set or with a
+/// vanishing `dz`, are black rather than `NaN`.
+pub fn distance_estimate_shade(cell: &GridCell, pixel_size: f64) -> DVec3 {
+    match cell.distance_estimate() {
+        Some(d) if d.is_finite() => {
+            let t = (d / pixel_size).clamp(0., 1.);
+            DVec3::broadcast(1. - t)
+        }
+        _ => DVec3::broadcast(0.),
+    }
+}
+
+/// Convert a hue in turns (`0.0` is red, wrapping at `1.0`) to a fully saturated, full value color
+fn hue_to_rgb(hue: f64) -> DVec3 {
+    let h = 6. * hue.rem_euclid(1.);
+
+    let r = (h - 3.).abs() - 1.;
+    let g = 2. - (h - 2.).abs();
+    let b = 2. - (h - 4.).abs();
+
+    DVec3::new(r, g, b).clamped(DVec3::broadcast(0.), DVec3::broadcast(1.))
+}
+
+/// Convert a color from HSV to RGB
+///
+/// `hue` is in turns, so it wraps around every `1`, and `saturation` and `value` are in `[0, 1]`.
+pub fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> DVec3 {
+    let gray = DVec3::broadcast(1. - saturation);
+
+    value * (gray + saturation * hue_to_rgb(hue))
+}
+
+/// Color escaped cells by running their smooth iteration count around the color wheel
+///
+/// The hue goes around `cycles` times over `DEFAULT_MAX_ITERS` iterations, at full saturation and
+/// value. Cells that haven't escaped are black.
+pub fn with_hsv_cycle(cell: &GridCell, cycles: f64) -> DVec3 {
+    if !cell.has_escaped {
+        return DVec3::broadcast(0.);
+    }
+
+    let hue = cycles * cell.smooth_iters / DEFAULT_MAX_ITERS as f64;
+    hsv_to_rgb(hue, 1., 1.)
+}
+
+pub fn with_external_angle(cell: &GridCell) -> DVec3 {
+    // Color the rays by their external angle, and leave the set itself black
+    match crate::external_angle(cell.c, EXTERNAL_ANGLE_ITERS) {
+        Some(angle) => hue_to_rgb(angle),
+        None => DVec3::broadcast(0.),
+    }
+}
+
+/// Shade the "surface" like polished metal, reflecting the sky `env`
+///
+/// We look at the plane from 45 degrees below the frame (towards `+y` and down), reflect the view
+/// direction about `surface_normal`, and sample `env` by the elevation of the reflected ray: `0` is
+/// straight down, `1` is straight up. Cells that haven't escaped are black.
+pub fn with_env_reflection(cell: &GridCell, env: &Gradient) -> DVec3 {
+    if !cell.has_escaped {
+        return DVec3::broadcast(0.);
+    }
+
+    let n = surface_normal(cell).normalized();
+    let view = DVec3::new(0., 1., -1.).normalized();
+    let reflected = view - 2. * view.dot(n) * n;
+
+    let elevation = reflected.z.clamp(-1., 1.).asin();
+    env.sample(0.5 + elevation / std::f64::consts::PI)
+}
+
+/// `with_env_reflection` of a sky with dark ground, a bright horizon, and blue overhead
+pub fn with_chrome(cell: &GridCell) -> DVec3 {
+    static SKY: OnceLock<Gradient> = OnceLock::new();
+
+    let sky = SKY.get_or_init(|| Gradient::new(CHROME_SKY.to_vec()));
+    with_env_reflection(cell, sky)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SimConfig;
+
+    #[test]
+    fn suggested_stops_are_quantiles_in_unit_range() {
+        let config = SimConfig::builder()
+            .fb_dims(64, 48)
+            .build()
+            .expect("Expected a non-empty framebuffer");
+        let mut sim = Sim::new(config);
+        sim.run_until_done(u32::MAX);
+
+        let gradient = sim.suggest_palette();
+        let stops = gradient.stops();
+        assert_eq!(stops.len(), DEFAULT_COLORS.len());
+        assert!(stops.iter().all(|&(pos, _)| (0. ..=1.).contains(&pos)));
+        assert!(stops.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+
+        // The first color goes to the cells that escaped soonest, through a `Lut` too
+        let escaped = || sim.cells().iter().filter(|cell| cell.has_escaped);
+        let fastest = escaped().min_by_key(|cell| cell.iters).unwrap();
+        let max_iters = config.max_iters as f64;
+        assert_eq!(stops[0].0, fastest.iters as f64 / max_iters);
+        assert_eq!(
+            Lut::build(&gradient, config.max_iters).color(fastest),
+            DEFAULT_COLORS[0] / 255.
+        );
+
+        // Half of the escaped cells are at or below the middle stop
+        let middle = stops[stops.len() / 2].0 * max_iters;
+        let below = escaped().filter(|cell| cell.iters as f64 <= middle).count();
+        assert!(2 * below >= escaped().count());
+    }
 }
@@ -0,0 +1,253 @@
+//! Iterating the Mandelbrot set with perturbation theory
+//!
+//! Deep in a zoom, neighboring pixels have values of `c` that agree in most of their digits. Naive
+//! iteration keeps `z` around magnitude 1, so those shared digits crowd out the ones that tell the
+//! pixels apart, and the render turns blocky long before `c` itself runs out of precision.
+//!
+//! Instead, we iterate one reference point `C` at the center of the frame as `Z`, and every cell as a
+//! small offset from it: with `c = C + dc` and `z = Z + dz`,
+//!
+//! `dz' = 2 * Z * dz + dz^2 + dc`
+//!
+//! which only ever involves the small offsets, so they keep their full relative precision.
+//!
+//! The offset is only accurate while the cell's orbit stays near the reference orbit. Cells that
+//! wander off would glitch, so they're rebased onto the start of the reference orbit instead (see
+//! `step_perturbed`).
+
+use num::Complex;
+use ultraviolet::DVec2;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::math::ToComplex;
+use crate::{CoordTransform, Formula, FractalKind, GridCell, Sim, SimConfig, UpdateStats};
+
+/// The reference orbit, and each cell's offset from it
+pub(crate) struct Perturbation {
+    /// Orbit of the center of the frame, starting from `Z = 0`
+    ///
+    /// This runs until it escapes, or for `max_iters` iterations, whichever is first.
+    reference: Vec<Complex<f64>>,
+
+    /// The `max_iters` that `reference` was computed for
+    max_iters: u32,
+
+    /// One per cell of the grid
+    cells: Vec<PerturbedCell>,
+}
+
+/// A cell's offset from the reference orbit
+#[derive(Copy, Clone, Debug)]
+struct PerturbedCell {
+    /// Offset of this cell's `c` from the reference `C`
+    delta_c: Complex<f64>,
+
+    /// Offset of this cell's `z` from the reference orbit at `ref_iter`
+    delta_z: Complex<f64>,
+
+    /// Which point of the reference orbit `delta_z` is relative to
+    ///
+    /// This starts at the cell's iteration count, but drops back to `0` whenever the cell is rebased.
+    ref_iter: usize,
+
+    /// The cell's `iters` when this was last updated
+    ///
+    /// If the cell was stepped some other way since, like with `Sim::update`, this is out of date and
+    /// the cell is rebased from its current `z`.
+    iters: u32,
+}
+
+impl Perturbation {
+    fn new(config: &SimConfig, grid: &[GridCell]) -> Self {
+        let frame_center = 0.5 * (config.frame_min + config.frame_max);
+        let size = config.frame_max - config.frame_min;
+
+        let samples = config.samples_per_axis;
+        let grid_width = config.fb_dims.x * samples;
+
+        // Offsets come straight from the frame's size, so they stay precise even when the corners of
+        // the frame agree in most of their digits
+        let cells = grid
+            .iter()
+            .enumerate()
+            .map(|(idx, cell)| {
+                let x =
+                    ((idx as u32 % grid_width) as f64 / samples as f64) / config.fb_dims.x as f64;
+                let y =
+                    ((idx as u32 / grid_width) as f64 / samples as f64) / config.fb_dims.y as f64;
+
+                // Flip the buffer to put "bigger" y at the "top", like `SimConfig::idx_to_complex`
+                let t = DVec2::new(x - 0.5, 0.5 - y);
+
+                PerturbedCell {
+                    delta_c: (t * size).to_complex(),
+                    delta_z: cell.z,
+                    ref_iter: 0,
+                    iters: cell.iters,
+                }
+            })
+            .collect();
+
+        Self {
+            reference: reference_orbit(
+                frame_center.to_complex(),
+                config.max_iters,
+                config.step_radius_sqr,
+            ),
+            max_iters: config.max_iters,
+            cells,
+        }
+    }
+}
+
+/// Iterate `z = z^2 + c` from `0` until `|z|^2` passes `step_radius_sqr`, or for `max_iters`
+/// iterations
+///
+/// The result starts with `z = 0`, so it has at least one point.
+fn reference_orbit(c: Complex<f64>, max_iters: u32, step_radius_sqr: f64) -> Vec<Complex<f64>> {
+    let mut orbit = vec![Complex::new(0., 0.)];
+    let mut z = orbit[0];
+
+    for _ in 0..max_iters {
+        z = z * z + c;
+        orbit.push(z);
+
+        if z.norm_sqr() > step_radius_sqr {
+            break;
+        }
+    }
+
+    orbit
+}
+
+/// Advance `cell` by one iteration, through its offset `p` from `reference`
+///
+/// This mirrors `GridCell::step` for the standard quadratic Mandelbrot set, but without checking for
+/// periodic orbits: `z` is only as precise as the reference plus the offset, so comparing it against
+/// an earlier `z` isn't meaningful this deep.
+///
+/// Whenever `|z|` drops below `|delta_z|`, or the reference orbit runs out, the offset is rebased
+/// onto the start of the reference orbit (where `Z = 0`, so `delta_z = z`). This keeps `delta_z`
+/// small relative to `z`, which is what keeps the iteration accurate, and it's what stops cells from
+/// glitching when their orbit diverges from the reference.
+fn step_perturbed(
+    cell: &mut GridCell,
+    p: &mut PerturbedCell,
+    reference: &[Complex<f64>],
+    config: &SimConfig,
+) {
+    // Pick back up from wherever the cell actually is
+    if p.iters != cell.iters {
+        p.delta_z = cell.z;
+        p.ref_iter = 0;
+    }
+
+    if cell.is_done() {
+        return;
+    }
+    if cell.iters >= config.max_iters && !cell.has_escaped {
+        cell.reached_max = true;
+        return;
+    }
+
+    cell.iters += 1;
+    p.iters = cell.iters;
+
+    let big_z = reference[p.ref_iter];
+    cell.dz = cell.dz * 2. * cell.z + cell.dc;
+    p.delta_z = (big_z * 2. + p.delta_z) * p.delta_z + p.delta_c;
+    p.ref_iter += 1;
+
+    cell.z = reference[p.ref_iter] + p.delta_z;
+
+    if cell.z.norm_sqr() < p.delta_z.norm_sqr() || p.ref_iter + 1 >= reference.len() {
+        p.delta_z = cell.z;
+        p.ref_iter = 0;
+    }
+
+    if cell.z.norm_sqr() > 4.0 {
+        cell.has_escaped = true;
+    }
+
+    if cell.has_escaped && cell.z.norm_sqr() > config.step_radius_sqr {
+        cell.reached_bailout = true;
+    }
+
+    if cell.has_escaped {
+        cell.smooth_iters = cell.iters as f64 + 1. - cell.z.norm().log2().ln() / 2f64.ln();
+    }
+}
+
+impl Sim {
+    /// Like `update`, but iterate each cell as an offset from a reference orbit at the center of the
+    /// frame
+    ///
+    /// This stays accurate at zooms where `update` turns blocky, because the offsets keep their
+    /// precision even when every `c` in the frame shares most of its digits. It still can't zoom past
+    /// what an `f64` frame can describe, since `SimConfig` stores the frame's corners as `f64`s.
+    ///
+    /// This only applies to the standard, quadratic Mandelbrot set with `CoordTransform::Identity`.
+    /// Anything else, or a sim made `with_seeds`, is updated with `update` instead. Periodic orbits
+    /// aren't detected along this path, so interior cells (other than the main cardioid and bulb) run
+    /// until `max_iters`.
+    ///
+    /// The reference orbit is computed on the first call after a reset, and again if `max_iters`
+    /// changes. Cells stepped with `update` in between are picked up from where they are.
+    ///
+    /// Returns the same stats as `update`.
+    pub fn update_perturbed(&mut self) -> UpdateStats {
+        let config = self.config;
+        if config.kind != FractalKind::Mandelbrot
+            || config.formula != Formula::Standard
+            || config.power != 2
+            || config.transform != CoordTransform::Identity
+            || !self.pixel_grid
+        {
+            return self.update();
+        }
+        self.mirror_in_step = false;
+        self.mark_all_dirty();
+
+        let perturbation = match &mut self.perturbation {
+            Some(p) if p.max_iters == config.max_iters => p,
+            _ => self
+                .perturbation
+                .insert(Perturbation::new(&config, &self.grid)),
+        };
+        let reference = &perturbation.reference[..];
+
+        let step = |(cell, p): (&mut GridCell, &mut PerturbedCell)| {
+            let had_escaped = cell.has_escaped;
+            step_perturbed(cell, p, reference, &config);
+
+            UpdateStats::of_cell(cell, had_escaped)
+        };
+
+        #[cfg(feature = "rayon")]
+        {
+            self.grid
+                .par_iter_mut()
+                .zip(perturbation.cells.par_iter_mut())
+                .map(step)
+                .reduce(UpdateStats::default, UpdateStats::merge)
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            self.grid
+                .iter_mut()
+                .zip(perturbation.cells.iter_mut())
+                .map(step)
+                .fold(UpdateStats::default(), UpdateStats::merge)
+        }
+    }
+
+    /// Update the sim with `update_perturbed` `n` times in a row
+    pub fn update_perturbed_n(&mut self, n: u32) {
+        for _ in 0..n {
+            self.update_perturbed();
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Image-space processing on drawn framebuffers
+//!
+//! Everything in here works on the packed 0RGB `u32` pixels produced by `Sim::draw`.
+
+use ultraviolet::{DVec3, UVec2};
+
+use crate::rgb;
+
+/// Unpack a 0RGB pixel into channels in the range [0, 255]
+#[inline]
+fn unpack(px: u32) -> DVec3 {
+    let [_, r, g, b] = px.to_be_bytes();
+    DVec3::new(r as f64, g as f64, b as f64)
+}
+
+/// Pack channels in the range [0, 255] into a 0RGB pixel
+#[inline]
+fn pack(c: DVec3) -> u32 {
+    rgb(c.x.round() as u8, c.y.round() as u8, c.z.round() as u8)
+}
+
+/// Shrink a framebuffer from `src_dims` to `dst_dims` with a box filter
+///
+/// Each output pixel is the average of the block of input pixels it covers. `dst_dims` must be no
+/// larger than `src_dims` in either dimension.
+pub fn downsample(fb: &[u32], src_dims: UVec2, dst_dims: UVec2) -> Vec<u32> {
+    assert_eq!(fb.len(), (src_dims.x * src_dims.y) as usize);
+    assert!(dst_dims.x <= src_dims.x && dst_dims.y <= src_dims.y);
+
+    let mut out = Vec::with_capacity((dst_dims.x * dst_dims.y) as usize);
+
+    for y in 0..dst_dims.y {
+        // Rows of the source that land in this row of the output
+        let y0 = y * src_dims.y / dst_dims.y;
+        let y1 = (y + 1) * src_dims.y / dst_dims.y;
+
+        for x in 0..dst_dims.x {
+            let x0 = x * src_dims.x / dst_dims.x;
+            let x1 = (x + 1) * src_dims.x / dst_dims.x;
+
+            let mut sum = DVec3::broadcast(0.);
+            for sy in y0..y1 {
+                for sx in x0..x1 {
+                    sum += unpack(fb[(sy * src_dims.x + sx) as usize]);
+                }
+            }
+
+            let count = ((x1 - x0) * (y1 - y0)) as f64;
+            out.push(pack(sum / count));
+        }
+    }
+
+    out
+}
+
+/// Resample a scalar field from `src_dims` to `dst_dims` with bicubic (Catmull-Rom) interpolation
+///
+/// Output pixel `x` samples the source at `x * src_dims.x / dst_dims.x`, matching how pixels map
+/// into the complex plane. Samples beyond the edge of the source are linearly extrapolated, so a
+/// linear ramp stays exactly linear everywhere, including near the borders.
+pub fn bicubic_upscale_f32(field: &[f32], src_dims: UVec2, dst_dims: UVec2) -> Vec<f32> {
+    assert_eq!(field.len(), (src_dims.x * src_dims.y) as usize);
+
+    // Resample each row horizontally, then each column of that vertically
+    let mut rows = Vec::with_capacity((dst_dims.x * src_dims.y) as usize);
+    for row in field.chunks_exact(src_dims.x as usize) {
+        for x in 0..dst_dims.x {
+            let u = x as f64 * src_dims.x as f64 / dst_dims.x as f64;
+            rows.push(sample_cubic(|i| fetch(row, i), u) as f32);
+        }
+    }
+
+    let mut out = vec![0.; (dst_dims.x * dst_dims.y) as usize];
+    let mut column = vec![0.; src_dims.y as usize];
+    for x in 0..dst_dims.x as usize {
+        for (y, v) in column.iter_mut().enumerate() {
+            *v = rows[y * dst_dims.x as usize + x];
+        }
+
+        for y in 0..dst_dims.y as usize {
+            let v = y as f64 * src_dims.y as f64 / dst_dims.y as f64;
+            out[y * dst_dims.x as usize + x] = sample_cubic(|i| fetch(&column, i), v) as f32;
+        }
+    }
+
+    out
+}
+
+/// Luminance, in [0, 255], above which a pixel contributes to `bloom`
+const BLOOM_THRESHOLD: f64 = 0.6 * 255.;
+
+/// Make bright pixels glow outward
+///
+/// Pixels brighter than a threshold are extracted, blurred with a Gaussian of standard deviation
+/// `radius / 2`, scaled by `intensity`, and added back on top of `fb`. This works best after
+/// drawing a thin, bright boundary, like the distance estimate or iteration edges.
+///
+/// The blur is separable, so the cost is `O(width * height * radius)` rather than
+/// `O(width * height * radius^2)`: doubling the radius doubles the time. A `radius` of `0` leaves
+/// `fb` unchanged.
+pub fn bloom(fb: &mut [u32], dims: UVec2, radius: u32, intensity: f64) {
+    assert_eq!(fb.len(), (dims.x * dims.y) as usize);
+    if radius == 0 || fb.is_empty() {
+        return;
+    }
+
+    let (w, h) = (dims.x as usize, dims.y as usize);
+
+    // Keep only the bright parts
+    let bright: Vec<DVec3> = fb
+        .iter()
+        .map(|&px| {
+            let c = unpack(px);
+            let luma = 0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z;
+            if luma > BLOOM_THRESHOLD {
+                c
+            } else {
+                DVec3::broadcast(0.)
+            }
+        })
+        .collect();
+
+    // Normalized Gaussian weights for offsets 0..=radius
+    let sigma = radius as f64 / 2.;
+    let mut kernel: Vec<f64> = (0..=radius as i64)
+        .map(|i| (-(i * i) as f64 / (2. * sigma * sigma)).exp())
+        .collect();
+    let total = kernel[0] + 2. * kernel[1..].iter().sum::<f64>();
+    for k in &mut kernel {
+        *k /= total;
+    }
+
+    // Blur `src` along one axis, clamping reads to the edges
+    let blur = |src: &[DVec3], len: usize, at: &dyn Fn(usize, usize) -> usize| {
+        let mut out = vec![DVec3::broadcast(0.); src.len()];
+        let lines = src.len() / len;
+        for line in 0..lines {
+            for i in 0..len {
+                let mut sum = kernel[0] * src[at(line, i)];
+                for (d, &k) in kernel.iter().enumerate().skip(1) {
+                    let lo = i.saturating_sub(d);
+                    let hi = (i + d).min(len - 1);
+                    sum += k * (src[at(line, lo)] + src[at(line, hi)]);
+                }
+                out[at(line, i)] = sum;
+            }
+        }
+        out
+    };
+
+    let glow = blur(&bright, w, &|y, x| y * w + x);
+    let glow = blur(&glow, h, &|x, y| y * w + x);
+
+    for (px, g) in fb.iter_mut().zip(glow) {
+        *px = pack(unpack(*px) + intensity * g);
+    }
+}
+
+/// Read `values[i]`, linearly extrapolating from the nearest two values when `i` is out of bounds
+fn fetch(values: &[f32], i: i64) -> f64 {
+    let n = values.len() as i64;
+    let at = |i: i64| values[i as usize] as f64;
+
+    if n == 1 {
+        at(0)
+    } else if i < 0 {
+        at(0) + i as f64 * (at(1) - at(0))
+    } else if i >= n {
+        at(n - 1) + (i - (n - 1)) as f64 * (at(n - 1) - at(n - 2))
+    } else {
+        at(i)
+    }
+}
+
+/// Sample `f` at the fractional position `u` with a Catmull-Rom spline
+fn sample_cubic(f: impl Fn(i64) -> f64, u: f64) -> f64 {
+    let i = u.floor() as i64;
+    let t = u - u.floor();
+
+    let (p0, p1, p2, p3) = (f(i - 1), f(i), f(i + 1), f(i + 2));
+
+    0.5 * (2. * p1
+        + (-p0 + p2) * t
+        + (2. * p0 - 5. * p1 + 4. * p2 - p3) * t * t
+        + (-p0 + 3. * p1 - 3. * p2 + p3) * t * t * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bicubic_upscale_keeps_a_linear_ramp_linear() {
+        let src_dims = UVec2::new(8, 6);
+        let dst_dims = UVec2::new(29, 17);
+        let ramp = |x: f64, y: f64| (3. * x - 2. * y + 5.) as f32;
+
+        let field: Vec<f32> = (0..src_dims.y)
+            .flat_map(|y| (0..src_dims.x).map(move |x| ramp(x as f64, y as f64)))
+            .collect();
+        let upscaled = bicubic_upscale_f32(&field, src_dims, dst_dims);
+
+        for y in 0..dst_dims.y {
+            for x in 0..dst_dims.x {
+                let u = x as f64 * src_dims.x as f64 / dst_dims.x as f64;
+                let v = y as f64 * src_dims.y as f64 / dst_dims.y as f64;
+                let got = upscaled[(y * dst_dims.x + x) as usize];
+                assert!((got - ramp(u, v)).abs() < 1e-4, "({}, {}): {}", x, y, got);
+            }
+        }
+    }
+}
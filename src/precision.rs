@@ -0,0 +1,80 @@
+//! Comparing renders at different floating point precisions
+
+use crate::{Sim, SimConfig, SimFloat};
+
+/// Render the frame of `config` in both `f32` and `f64`, and find where they disagree
+///
+/// Both renders are `Sim`s made with `Sim::with_precision`, run until they're done with
+/// `config.max_iters` replaced by `max_iters` and one sample per pixel. The result is the absolute
+/// difference of each pixel's `iters` between the two. Points that don't escape count as
+/// `max_iters`.
+///
+/// Near the default zoom, the divergence should be negligible: only a handful of pixels right on the
+/// boundary differ. As the frame shrinks towards the spacing between `f32`s (roughly `1e-7` times
+/// the magnitude of `c`), neighboring pixels collapse onto the same `f32` and the map lights up
+/// everywhere, which is a good sign that it's time for `f64`.
+pub fn precision_divergence(config: SimConfig, max_iters: u32) -> Vec<u32> {
+    let config = SimConfig {
+        max_iters,
+        samples_per_axis: 1,
+        ..config
+    };
+
+    let low = escape_iters(Sim::<f32>::with_precision(config));
+    let high = escape_iters(Sim::<f64>::with_precision(config));
+
+    low.iter()
+        .zip(&high)
+        .map(|(low, high)| low.abs_diff(*high))
+        .collect()
+}
+
+/// Run `sim` until it's done, and count the iterations of each cell, or `max_iters` if it never
+/// escaped
+fn escape_iters<F: SimFloat>(mut sim: Sim<F>) -> Vec<u32> {
+    sim.run_until_done(u32::MAX);
+
+    let max_iters = sim.config().max_iters;
+    sim.cells()
+        .iter()
+        .map(|cell| {
+            if cell.has_escaped {
+                cell.iters
+            } else {
+                max_iters
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::make_square_frame;
+    use ultraviolet::DVec2;
+
+    #[test]
+    fn divergence_is_quiet_at_default_zoom_and_loud_when_deep() {
+        let config = SimConfig::builder()
+            .fb_dims(64, 48)
+            .build()
+            .expect("Expected a non-empty framebuffer");
+        let shallow = precision_divergence(config, 256);
+        assert_eq!(shallow.len(), 64 * 48);
+        let differing = shallow.iter().filter(|&&d| d > 0).count();
+        assert!(differing < shallow.len() / 20, "{}", differing);
+
+        let (frame_min, frame_max) =
+            make_square_frame(DVec2::new(-0.743643887037151, 0.131825904205330), 1e-8);
+        let deep = precision_divergence(
+            SimConfig {
+                frame_min,
+                frame_max,
+                ..config
+            },
+            2_000,
+        );
+        let differing = deep.iter().filter(|&&d| d > 0).count();
+        assert!(differing > deep.len() / 4, "{}", differing);
+    }
+}
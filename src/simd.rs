@@ -0,0 +1,326 @@
+//! Stepping cells four at a time with SIMD
+//!
+//! Every cell of the standard, quadratic Mandelbrot set runs the same `z = z^2 + c` update, so
+//! `Sim::update_simd` packs four cells into the lanes of `f64x4`s and steps them together. Each lane
+//! follows `GridCell::step` exactly, one operation at a time, so the results match `Sim::update`
+//! bit for bit. Lanes that are done are masked off, and keep their old values.
+
+use wide::{f64x4, CmpEq, CmpGe, CmpGt, CmpLt};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::{
+    Formula, GridCell, Sim, SimConfig, UpdateStats, PERIOD_CHECK_INTERVAL, PERIOD_EPSILON,
+};
+
+const LANES: usize = 4;
+
+/// Number of cells each rayon task steps
+#[cfg(feature = "rayon")]
+const CHUNK: usize = 64 * LANES;
+
+/// Up to `LANES` cells, one per lane
+///
+/// Flags are masks, with every bit of a lane set for `true`.
+struct Lanes {
+    z: (f64x4, f64x4),
+    dz: (f64x4, f64x4),
+    c: (f64x4, f64x4),
+    dc: (f64x4, f64x4),
+    z_ref: (f64x4, f64x4),
+    iters: f64x4,
+
+    has_escaped: f64x4,
+    reached_max: f64x4,
+    is_interior: f64x4,
+    reached_bailout: f64x4,
+
+    /// Lanes that have been stepped at least once
+    stepped: f64x4,
+}
+
+/// A mask with the lanes of `flags` set
+#[inline]
+fn mask(flags: [bool; LANES]) -> f64x4 {
+    f64x4::new(flags.map(|flag| if flag { f64::from_bits(!0) } else { 0. }))
+}
+
+/// Whether `lane` of `mask` is set
+#[inline]
+fn lane(mask: f64x4, lane: usize) -> bool {
+    (mask.move_mask() >> lane) & 1 == 1
+}
+
+impl Lanes {
+    fn load(cells: &[GridCell]) -> Self {
+        // Lanes past the end of `cells` are padded with cells that are already done
+        let mut values = [[0.; LANES]; 11];
+        let mut flags = [[true; LANES]; 4];
+
+        for (i, cell) in cells.iter().enumerate() {
+            let GridCell {
+                z,
+                dz,
+                c,
+                dc,
+                z_ref,
+                ..
+            } = *cell;
+
+            for (value, x) in values.iter_mut().zip([
+                z.re,
+                z.im,
+                dz.re,
+                dz.im,
+                c.re,
+                c.im,
+                dc.re,
+                dc.im,
+                z_ref.re,
+                z_ref.im,
+                cell.iters as f64,
+            ]) {
+                value[i] = x;
+            }
+
+            for (flag, x) in flags.iter_mut().zip([
+                cell.has_escaped,
+                cell.reached_max,
+                cell.is_interior,
+                cell.reached_bailout,
+            ]) {
+                flag[i] = x;
+            }
+        }
+
+        let [zr, zi, dzr, dzi, cr, ci, dcr, dci, rr, ri, iters] = values.map(f64x4::new);
+        let [has_escaped, reached_max, is_interior, reached_bailout] = flags.map(mask);
+
+        Self {
+            z: (zr, zi),
+            dz: (dzr, dzi),
+            c: (cr, ci),
+            dc: (dcr, dci),
+            z_ref: (rr, ri),
+            iters,
+
+            has_escaped,
+            reached_max,
+            is_interior,
+            reached_bailout,
+            stepped: mask([false; LANES]),
+        }
+    }
+
+    fn store(&self, cells: &mut [GridCell]) {
+        let get = |v: f64x4, i: usize| v.to_array()[i];
+
+        for (i, cell) in cells.iter_mut().enumerate() {
+            if !lane(self.stepped, i) {
+                // `reached_max` may still have been set, without stepping
+                cell.reached_max = lane(self.reached_max, i);
+                continue;
+            }
+
+            cell.z.re = get(self.z.0, i);
+            cell.z.im = get(self.z.1, i);
+            cell.dz.re = get(self.dz.0, i);
+            cell.dz.im = get(self.dz.1, i);
+            cell.z_ref.re = get(self.z_ref.0, i);
+            cell.z_ref.im = get(self.z_ref.1, i);
+            cell.iters = get(self.iters, i) as u32;
+
+            cell.has_escaped = lane(self.has_escaped, i);
+            cell.reached_max = lane(self.reached_max, i);
+            cell.is_interior = lane(self.is_interior, i);
+            cell.reached_bailout = lane(self.reached_bailout, i);
+
+            // Only the last step's value survives, so there's no need to compute it on every step
+            if cell.has_escaped {
+                let log_z = cell.z.norm().log2().ln();
+                cell.smooth_iters = cell.iters as f64 + 1. - log_z / 2f64.ln();
+            }
+        }
+    }
+
+    /// Step every lane that isn't done `n` times, like `GridCell::step`
+    fn step_n(&mut self, config: &SimConfig, n: u32) {
+        let max_iters = f64x4::splat(config.max_iters as f64);
+        let interval = f64x4::splat(PERIOD_CHECK_INTERVAL as f64);
+        let escape = f64x4::splat(4.);
+        let epsilon = f64x4::splat(PERIOD_EPSILON);
+        let bailout = f64x4::splat(config.step_radius_sqr);
+        let zero = f64x4::splat(0.);
+        let one = f64x4::splat(1.);
+
+        for _ in 0..n {
+            let done = self.reached_max | self.is_interior | self.reached_bailout;
+            let at_max = !done & !self.has_escaped & self.iters.cmp_ge(max_iters);
+            self.reached_max |= at_max;
+
+            let active = !(done | at_max);
+            if active.none() {
+                break;
+            }
+            self.stepped |= active;
+            self.iters += active.blend(one, zero);
+
+            // The same operations as `z * z + c` and `dz * 2. * z + dc` on `Complex<f64>`
+            let (zr, zi) = self.z;
+            let (dzr, dzi) = (self.dz.0 * 2., self.dz.1 * 2.);
+            let new_z = (zr * zr - zi * zi + self.c.0, zr * zi + zi * zr + self.c.1);
+            let new_dz = (
+                dzr * zr - dzi * zi + self.dc.0,
+                dzr * zi + dzi * zr + self.dc.1,
+            );
+
+            self.z = (
+                active.blend(new_z.0, self.z.0),
+                active.blend(new_z.1, self.z.1),
+            );
+            self.dz = (
+                active.blend(new_dz.0, self.dz.0),
+                active.blend(new_dz.1, self.dz.1),
+            );
+
+            let norm_sqr = self.z.0 * self.z.0 + self.z.1 * self.z.1;
+            self.has_escaped |= active & norm_sqr.cmp_gt(escape);
+
+            // An orbit that comes back to where it was is periodic, and will never escape
+            let bounded = active & !self.has_escaped;
+            let (dr, di) = (self.z.0 - self.z_ref.0, self.z.1 - self.z_ref.1);
+            let cycled = bounded & (dr * dr + di * di).cmp_lt(epsilon);
+            self.is_interior |= cycled;
+
+            let on_interval =
+                (self.iters - (self.iters / interval).floor() * interval).cmp_eq(zero);
+            let save = bounded & !cycled & on_interval;
+            self.z_ref = (
+                save.blend(self.z.0, self.z_ref.0),
+                save.blend(self.z.1, self.z_ref.1),
+            );
+
+            self.reached_bailout |= active & self.has_escaped & norm_sqr.cmp_gt(bailout);
+        }
+    }
+}
+
+/// Step `cells` `n` times, four at a time, and count what happened to them
+fn step_cells(cells: &mut [GridCell], config: &SimConfig, n: u32) -> UpdateStats {
+    let mut stats = UpdateStats::default();
+
+    for cells in cells.chunks_mut(LANES) {
+        // Most of the grid is done long before the rest, so don't bother loading it
+        if cells.iter().all(GridCell::is_done) {
+            continue;
+        }
+
+        let had_escaped: [bool; LANES] =
+            std::array::from_fn(|i| cells.get(i).is_some_and(|cell| cell.has_escaped));
+
+        let mut lanes = Lanes::load(cells);
+        lanes.step_n(config, n);
+        lanes.store(cells);
+
+        for (cell, &had_escaped) in cells.iter().zip(&had_escaped) {
+            stats = stats.merge(UpdateStats::of_cell(cell, had_escaped));
+        }
+    }
+
+    stats
+}
+
+impl Sim {
+    /// Like `update`, but step four cells at a time with SIMD
+    ///
+    /// This gives exactly the same results as `update`. It only applies to the standard, quadratic
+    /// formula (either fractal kind), and anything else is updated with `update` instead.
+    ///
+    /// A single step spends most of its time moving cells in and out of registers, so it's only
+    /// about as fast as `update`. Use `update_simd_n` to step many times at once, which is where the
+    /// speedup is: `benches/simd.rs` runs a render to the end both ways.
+    pub fn update_simd(&mut self) -> UpdateStats {
+        self.update_simd_n(1)
+    }
+
+    /// Like `update_n`, but step four cells at a time with SIMD
+    ///
+    /// Cells stay in SIMD registers for all `n` steps, so this is faster than calling `update_simd`
+    /// `n` times. Returns how many cells are still active, and how many escaped during the `n` steps.
+    pub fn update_simd_n(&mut self, n: u32) -> UpdateStats {
+        let config = self.config;
+        if config.formula != Formula::Standard || config.power != 2 {
+            let mut stats = UpdateStats::default();
+            for _ in 0..n {
+                let step = self.update();
+                stats = UpdateStats {
+                    active: step.active,
+                    escaped_this_step: stats.escaped_this_step + step.escaped_this_step,
+                };
+            }
+            return stats;
+        }
+        self.mark_all_dirty();
+
+        #[cfg(feature = "rayon")]
+        {
+            self.grid
+                .par_chunks_mut(CHUNK)
+                .map(|cells| step_cells(cells, &config, n))
+                .reduce(UpdateStats::default, UpdateStats::merge)
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            step_cells(&mut self.grid, &config, n)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::Complex;
+
+    use crate::FractalKind;
+
+    #[test]
+    fn simd_steps_match_update_bit_for_bit() {
+        let mirrored = SimConfig::builder()
+            .fb_dims(61, 47)
+            .build()
+            .expect("Expected a non-empty framebuffer");
+        let julia = SimConfig {
+            kind: FractalKind::Julia {
+                c: Complex::new(-0.8, 0.156),
+            },
+            ..mirrored
+        };
+        let off_center = SimConfig::builder()
+            .fb_dims(61, 47)
+            .center(ultraviolet::DVec2::new(-0.75, 0.1))
+            .zoom(0.05)
+            .build()
+            .expect("Expected a non-empty framebuffer");
+
+        for config in [mirrored, julia, off_center] {
+            let mut expected = Sim::new(config);
+            let mut single = Sim::new(config);
+            let mut many = Sim::new(config);
+
+            // Step in lockstep for a while, so every step is compared, then run to the end
+            for _ in 0..40 {
+                let stats = expected.update();
+                assert_eq!(single.update_simd(), stats);
+                assert!(single.grid == expected.grid);
+            }
+            expected.run_until_done(u32::MAX);
+            while single.update_simd_n(1).active > 0 {}
+            assert!(single.grid == expected.grid);
+
+            while many.update_simd_n(7).active > 0 {}
+            assert!(many.grid == expected.grid);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! Previewing the sim in a terminal, with 24-bit ANSI colors
+
+use std::io::{self, Write};
+
+use ultraviolet::DVec3;
+
+use crate::{encode, GridCell, Sim};
+
+/// Upper half block: its foreground colors the top pixel, and its background the bottom
+const HALF_BLOCK: char = '\u{2580}';
+
+/// Resets the colors back to the terminal's defaults
+const RESET: &str = "\x1b[0m";
+
+impl Sim {
+    /// Draw the grid with `color` as `cols` by `rows` characters of ANSI truecolor text
+    ///
+    /// Each character is a half block with separate foreground and background colors, so it shows
+    /// two pixels stacked vertically: the preview is `cols` by `2 * rows` pixels. Each of those is
+    /// the average of the framebuffer pixels it covers. Colors are reset at the end of every line, so
+    /// the terminal is left as it was.
+    ///
+    /// Terminal characters are usually about twice as tall as they are wide, so pick `rows` around
+    /// `cols * fb_dims.y / fb_dims.x / 2` to keep the aspect ratio.
+    pub fn render_to_terminal<ColorFn>(
+        &self,
+        color: ColorFn,
+        out: &mut impl Write,
+        cols: u32,
+        rows: u32,
+    ) -> io::Result<()>
+    where
+        ColorFn: Fn(&GridCell) -> DVec3 + Sync,
+    {
+        let fb_dims = self.config.fb_dims;
+        let (width, height) = (cols as usize, 2 * rows as usize);
+
+        // Average the framebuffer pixels in the block that preview pixel `(x, y)` covers
+        let preview_pixel = |x: usize, y: usize| -> [u8; 3] {
+            let span = |i: usize, n: usize, fb_n: u32| {
+                let begin = i * fb_n as usize / n;
+                let end = ((i + 1) * fb_n as usize / n).max(begin + 1);
+                begin..end.min(fb_n as usize)
+            };
+            let (xs, ys) = (span(x, width, fb_dims.x), span(y, height, fb_dims.y));
+
+            let mut sum = DVec3::zero();
+            let mut count = 0;
+            for fb_y in ys {
+                for fb_x in xs.clone() {
+                    let c = self.pixel_color(fb_y * fb_dims.x as usize + fb_x, &color);
+                    sum += c.clamped(DVec3::broadcast(0.), DVec3::broadcast(1.));
+                    count += 1;
+                }
+            }
+
+            let [_, r, g, b] = encode(sum / count.max(1) as f64).to_be_bytes();
+            [r, g, b]
+        };
+
+        for row in 0..rows as usize {
+            for x in 0..width {
+                let [r0, g0, b0] = preview_pixel(x, 2 * row);
+                let [r1, g1, b1] = preview_pixel(x, 2 * row + 1);
+                write!(
+                    out,
+                    "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m{}",
+                    r0, g0, b0, r1, g1, b1, HALF_BLOCK
+                )?;
+            }
+            writeln!(out, "{}", RESET)?;
+        }
+
+        out.flush()
+    }
+}
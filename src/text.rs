@@ -0,0 +1,116 @@
+//! A tiny bitmap font for labelling framebuffers
+//!
+//! Glyphs are 5x7 pixels and cover digits, letters (lowercase is drawn as uppercase), and a handful
+//! of punctuation. Anything else is drawn as a blank space.
+
+use ultraviolet::UVec2;
+
+/// Width of a glyph in font pixels, not counting spacing
+pub const GLYPH_WIDTH: u32 = 5;
+
+/// Height of a glyph in font pixels
+pub const GLYPH_HEIGHT: u32 = 7;
+
+/// Horizontal distance between the start of each glyph, in font pixels
+const ADVANCE: u32 = GLYPH_WIDTH + 1;
+
+/// Look up the rows for a glyph, top to bottom. The high bit of each 5-bit row is the leftmost pixel.
+fn glyph(ch: char) -> [u8; 7] {
+    match ch.to_ascii_uppercase() {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x11, 0x1F, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+        '=' => [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        _ => [0x00; 7],
+    }
+}
+
+/// Size in framebuffer pixels of `text` drawn at `scale`
+pub fn text_dims(text: &str, scale: u32) -> UVec2 {
+    let len = text.chars().count() as u32;
+    let width = if len == 0 { 0 } else { len * ADVANCE - 1 };
+
+    UVec2::new(width * scale, GLYPH_HEIGHT * scale)
+}
+
+/// Draw `text` into a 0RGB framebuffer with its top-left corner at `pos`
+///
+/// Each font pixel is drawn as a `scale`x`scale` block. Pixels that fall outside of `fb_dims` are
+/// clipped.
+pub fn draw_text(fb: &mut [u32], fb_dims: UVec2, pos: UVec2, text: &str, scale: u32, color: u32) {
+    assert_eq!(fb.len(), (fb_dims.x * fb_dims.y) as usize);
+
+    for (i, ch) in text.chars().enumerate() {
+        let origin_x = pos.x + i as u32 * ADVANCE * scale;
+
+        for (row, bits) in glyph(ch).iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - col)) == 0 {
+                    continue;
+                }
+
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let x = origin_x + col * scale + dx;
+                        let y = pos.y + row as u32 * scale + dy;
+
+                        if x < fb_dims.x && y < fb_dims.y {
+                            fb[(y * fb_dims.x + x) as usize] = color;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Draw `text` like `draw_text`, with a one font-pixel black drop shadow so it's legible on any
+/// background
+pub fn draw_label(fb: &mut [u32], fb_dims: UVec2, pos: UVec2, text: &str, scale: u32) {
+    let shadow = UVec2::new(pos.x + scale, pos.y + scale);
+
+    draw_text(fb, fb_dims, shadow, text, scale, 0x00_00_00_00);
+    draw_text(fb, fb_dims, pos, text, scale, 0x00_ff_ff_ff);
+}
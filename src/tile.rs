@@ -0,0 +1,324 @@
+//! Splitting the framebuffer into tiles
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use ultraviolet::{DVec2, DVec3, UVec2};
+
+use crate::{GridCell, Sim, SimConfig};
+
+impl SimConfig {
+    /// The config for the `dims` pixels of this frame starting at pixel `origin`
+    ///
+    /// The tile's frame covers exactly the part of this frame that those pixels sample, so its pixels
+    /// land on the same points of the complex plane, up to rounding.
+    fn tile(&self, origin: UVec2, dims: UVec2) -> SimConfig {
+        let size = self.frame_max - self.frame_min;
+        let point = |x: u32, y: u32| {
+            // Like `pixel_to_complex`, rows go down from `frame_max.y`
+            let t = DVec2::new(
+                x as f64 / self.fb_dims.x as f64,
+                1. - y as f64 / self.fb_dims.y as f64,
+            );
+            self.frame_min + t * size
+        };
+
+        let top_left = point(origin.x, origin.y);
+        let bottom_right = point(origin.x + dims.x, origin.y + dims.y);
+
+        SimConfig {
+            fb_dims: dims,
+            frame_min: DVec2::new(top_left.x, bottom_right.y),
+            frame_max: DVec2::new(bottom_right.x, top_left.y),
+            ..*self
+        }
+    }
+}
+
+impl Sim {
+    /// Render `config` in tiles, handing each finished tile to `emit`
+    ///
+    /// Only the tiles being worked on have a `Sim` alive, one per thread at most, so memory use
+    /// depends on `tile_dims`, not on `config.fb_dims`. That makes images too big for a single grid,
+    /// like 16384x16384, possible. Each tile is run until it's done, drawn with `color`, and passed
+    /// to `emit(tile_origin, tile_dims, pixels)`, where `pixels` is laid out like `draw`'s
+    /// framebuffer for the tile. The tiles on the right and bottom edges are cut down to fit.
+    ///
+    /// With the `rayon` feature, tiles are rendered in parallel: the pool splits
+    /// `hilbert_tile_order` into runs, so each thread works through a compact patch of the image and
+    /// steals the rest of another thread's run once its own is done. `emit` is called from those
+    /// threads, one tile at a time, in no particular order.
+    ///
+    /// Every cell of a tile is seeded with exactly the point that the same cell of a single-pass
+    /// render would sample, so the tiles line up without seams. A tile's own frame (from
+    /// `Sim::config`) is the part of `config`'s frame that it covers.
+    pub fn render_tiled<ColorFn>(
+        config: &SimConfig,
+        tile_dims: UVec2,
+        color: ColorFn,
+        emit: impl FnMut(UVec2, UVec2, &[u32]) + Send,
+    ) where
+        ColorFn: Fn(&GridCell) -> DVec3 + Sync,
+    {
+        let tiles = tile_counts(config.fb_dims, tile_dims);
+        let order = hilbert_tile_order(tiles.x, tiles.y);
+
+        #[cfg(feature = "rayon")]
+        {
+            let emit = std::sync::Mutex::new(emit);
+            order
+                .par_iter()
+                .for_each_init(Vec::new, |framebuffer, &tile_idx| {
+                    let (origin, dims) =
+                        render_tile(config, tile_dims, tile_idx, &color, framebuffer);
+                    let mut emit = emit.lock().expect("Expected emit not to panic");
+                    emit(origin, dims, framebuffer);
+                });
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            render_tiles_in_order(config, tile_dims, &order, color, emit);
+        }
+    }
+
+    /// Like `render_tiled`, but visit the tiles left to right, then top to bottom
+    ///
+    /// Tiles are rendered one at a time, on the calling thread, and every band of rows is finished
+    /// before the next one starts. That's what streaming the image out a row at a time (like
+    /// `save_png_tiled`) needs.
+    pub fn render_tiled_by_rows<ColorFn>(
+        config: &SimConfig,
+        tile_dims: UVec2,
+        color: ColorFn,
+        emit: impl FnMut(UVec2, UVec2, &[u32]),
+    ) where
+        ColorFn: Fn(&GridCell) -> DVec3 + Sync,
+    {
+        let tiles = tile_counts(config.fb_dims, tile_dims);
+        let order: Vec<usize> = (0..(tiles.x * tiles.y) as usize).collect();
+        render_tiles_in_order(config, tile_dims, &order, color, emit);
+    }
+}
+
+/// How many tiles of `tile_dims` it takes to cover `fb_dims`, along each axis
+fn tile_counts(fb_dims: UVec2, tile_dims: UVec2) -> UVec2 {
+    assert!(
+        tile_dims.x > 0 && tile_dims.y > 0,
+        "Expected tiles with at least one pixel"
+    );
+
+    UVec2::new(
+        fb_dims.x.div_ceil(tile_dims.x),
+        fb_dims.y.div_ceil(tile_dims.y),
+    )
+}
+
+/// Render the tiles of `config` named by `order`, by their row-major index, one after another
+fn render_tiles_in_order<ColorFn>(
+    config: &SimConfig,
+    tile_dims: UVec2,
+    order: &[usize],
+    color: ColorFn,
+    mut emit: impl FnMut(UVec2, UVec2, &[u32]),
+) where
+    ColorFn: Fn(&GridCell) -> DVec3 + Sync,
+{
+    let mut framebuffer = vec![];
+    for &tile_idx in order {
+        let (origin, dims) = render_tile(config, tile_dims, tile_idx, &color, &mut framebuffer);
+        emit(origin, dims, &framebuffer);
+    }
+}
+
+/// Render the tile of `config` at row-major index `tile_idx` into `framebuffer`
+///
+/// Returns the tile's origin and dims, for `Sim::render_tiled`'s `emit`.
+fn render_tile<ColorFn>(
+    config: &SimConfig,
+    tile_dims: UVec2,
+    tile_idx: usize,
+    color: &ColorFn,
+    framebuffer: &mut Vec<u32>,
+) -> (UVec2, UVec2)
+where
+    ColorFn: Fn(&GridCell) -> DVec3 + Sync,
+{
+    let full = config.fb_dims;
+    let tiles_x = tile_counts(full, tile_dims).x as usize;
+    let samples = config.samples_per_axis;
+    let grid_width = config.grid_dims().x;
+
+    let origin = UVec2::new(
+        (tile_idx % tiles_x) as u32 * tile_dims.x,
+        (tile_idx / tiles_x) as u32 * tile_dims.y,
+    );
+    let dims = UVec2::new(
+        tile_dims.x.min(full.x - origin.x),
+        tile_dims.y.min(full.y - origin.y),
+    );
+
+    // Seed from the full grid's points, instead of mapping through the tile's frame
+    let tile = config.tile(origin, dims);
+    let grid_dims = tile.grid_dims();
+    let seeds = (0..grid_dims.y)
+        .flat_map(|y| (0..grid_dims.x).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let (x, y) = (origin.x * samples + x, origin.y * samples + y);
+            config.grid_idx_to_complex(y * grid_width + x)
+        })
+        .collect();
+
+    let mut sim = Sim::with_seeds(tile, seeds);
+    sim.run_until_done(u32::MAX);
+
+    framebuffer.resize((dims.x * dims.y) as usize, 0);
+    sim.draw(framebuffer, color);
+    (origin, dims)
+}
+
+/// Order to visit a `tiles_x` by `tiles_y` grid of tiles in, following a Hilbert curve
+///
+/// Consecutive tiles along a Hilbert curve are always neighbors, so tiles that are processed close
+/// together in time are also close together in the image. The boundary of the set is both expensive
+/// and spatially coherent, so this keeps similar work together.
+///
+/// Tiles are identified by their row-major index, `y * tiles_x + x`, and every tile appears exactly
+/// once. Grids that aren't a square power of two walk the curve of the next power of two up, and skip
+/// the tiles that fall outside of the grid.
+pub fn hilbert_tile_order(tiles_x: u32, tiles_y: u32) -> Vec<usize> {
+    let side = tiles_x.max(tiles_y).max(1).next_power_of_two();
+
+    let mut order = Vec::with_capacity((tiles_x * tiles_y) as usize);
+    for d in 0..(side as u64 * side as u64) {
+        let (x, y) = hilbert_d_to_xy(side, d);
+        if x < tiles_x && y < tiles_y {
+            order.push((y * tiles_x + x) as usize);
+        }
+    }
+
+    order
+}
+
+/// Find the point at distance `d` along the Hilbert curve that fills a `side` by `side` square
+///
+/// `side` must be a power of two.
+fn hilbert_d_to_xy(side: u32, d: u64) -> (u32, u32) {
+    let (mut x, mut y) = (0, 0);
+    let mut t = d;
+
+    let mut s = 1;
+    while s < side {
+        let rx = (1 & (t / 2)) as u32;
+        let ry = (1 & (t ^ rx as u64)) as u32;
+
+        // Rotate the quadrant so the sub-curves connect
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+
+        x += s * rx;
+        y += s * ry;
+        t /= 4;
+        s *= 2;
+    }
+
+    (x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette;
+
+    /// Assemble the tiles from `render` into one framebuffer, checking every pixel is drawn once
+    fn assemble(
+        config: &SimConfig,
+        tile_dims: UVec2,
+        render: impl Fn(
+            &SimConfig,
+            UVec2,
+            fn(&GridCell) -> DVec3,
+            &mut (dyn FnMut(UVec2, UVec2, &[u32]) + Send),
+        ),
+    ) -> Vec<u32> {
+        let dims = config.fb_dims;
+        let mut framebuffer = vec![None; (dims.x * dims.y) as usize];
+        render(
+            config,
+            tile_dims,
+            palette::with_plain_colors,
+            &mut |origin, tile_dims, pixels| {
+                for (y, row) in pixels.chunks_exact(tile_dims.x as usize).enumerate() {
+                    for (x, &pixel) in row.iter().enumerate() {
+                        let idx = (origin.y as usize + y) * dims.x as usize + origin.x as usize + x;
+                        assert!(framebuffer[idx].replace(pixel).is_none());
+                    }
+                }
+            },
+        );
+        framebuffer.into_iter().map(Option::unwrap).collect()
+    }
+
+    #[test]
+    fn tile_orders_draw_the_same_image() {
+        let config = SimConfig::builder()
+            .fb_dims(100, 70)
+            .build()
+            .expect("Expected a non-empty framebuffer");
+
+        let tile_dims = UVec2::new(24, 20);
+        let hilbert = assemble(&config, tile_dims, |config, tile_dims, color, emit| {
+            Sim::render_tiled(config, tile_dims, color, emit)
+        });
+        let rows = assemble(&config, tile_dims, |config, tile_dims, color, emit| {
+            Sim::render_tiled_by_rows(config, tile_dims, color, emit)
+        });
+        assert!(hilbert == rows);
+    }
+
+    #[test]
+    fn tiles_line_up_exactly_with_a_single_pass_render() {
+        let config = SimConfig::builder()
+            .fb_dims(200, 150)
+            .build()
+            .expect("Expected a non-empty framebuffer");
+
+        let mut sim = Sim::new(config);
+        sim.run_until_done(u32::MAX);
+        let mut single_pass = vec![0; (config.fb_dims.x * config.fb_dims.y) as usize];
+        sim.draw(&mut single_pass, &palette::with_plain_colors);
+
+        // Neither size divides the frame, so the last row and column of tiles are cut down
+        for tile_dims in [UVec2::new(64, 64), UVec2::new(48, 36)] {
+            let tiled = assemble(&config, tile_dims, |config, tile_dims, color, emit| {
+                Sim::render_tiled(config, tile_dims, color, emit)
+            });
+            let differing = tiled
+                .iter()
+                .zip(&single_pass)
+                .filter(|(a, b)| a != b)
+                .count();
+            assert_eq!(differing, 0, "with {:?} tiles", tile_dims);
+        }
+    }
+
+    #[test]
+    fn hilbert_order_visits_every_tile_once_through_neighbors() {
+        let order = hilbert_tile_order(5, 3);
+
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..15).collect::<Vec<_>>());
+
+        // Skipping tiles outside of the grid can break the chain, but a full square never does
+        let order = hilbert_tile_order(8, 8);
+        for pair in order.windows(2) {
+            let (a, b) = ((pair[0] % 8, pair[0] / 8), (pair[1] % 8, pair[1] / 8));
+            assert_eq!(a.0.abs_diff(b.0) + a.1.abs_diff(b.1), 1);
+        }
+    }
+}